@@ -0,0 +1,66 @@
+use windows::Win32::UI::Accessibility::*;
+use windows::core::BSTR;
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use crate::inspector::{Selector, UiInspector};
+use crate::window_manager::{DialogWindow, WindowBackend};
+
+/// An open dialog with the text it shows and the buttons that close it.
+#[derive(Serialize, Debug)]
+pub struct Dialog {
+    #[serde(flatten)]
+    pub window: DialogWindow,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub text: Vec<String>,
+    pub buttons: Vec<String>,
+}
+
+/// Dialogs currently open, topmost first. `process_id` keeps only the target app's dialogs
+/// (`None` keeps every app's); `modal_only` drops modeless ones.
+pub fn detect(backend: &dyn WindowBackend, inspector: &UiInspector, process_id: Option<u32>, modal_only: bool) -> Result<Vec<Dialog>> {
+    let dialogs = backend
+        .list_dialogs()?
+        .into_iter()
+        .filter(|dialog| process_id.is_none_or(|pid| dialog.window.process_id == pid))
+        .filter(|dialog| dialog.modal || !modal_only)
+        .map(|window| {
+            let element = inspector.element_from_handle(window.window.handle).ok();
+            let names = |control_type: &str| {
+                element.as_ref().map(|el| named_descendants(inspector, el, control_type)).unwrap_or_default()
+            };
+            Dialog { text: names("text"), buttons: names("button"), window }
+        })
+        .collect();
+    Ok(dialogs)
+}
+
+/// Presses the button named `button` (case-insensitive, mnemonics ignored) in dialog `handle`.
+pub fn dismiss(inspector: &UiInspector, handle: u64, button: &str) -> Result<()> {
+    let dialog = inspector.element_from_handle(handle)?;
+    let buttons = inspector.find_in(&dialog, TreeScope_Descendants, &Selector { control_type: Some("button".to_string()), ..Selector::default() })?;
+    let wanted = label(button);
+    let target = buttons.iter().find(|b| label(&element_name(b)) == wanted).ok_or_else(|| {
+        let available: Vec<String> = buttons.iter().map(element_name).filter(|name| !name.is_empty()).collect();
+        anyhow!("No button '{}' in the dialog; available: {}", button, available.join(", "))
+    })?;
+    inspector.invoke(target)
+}
+
+fn named_descendants(inspector: &UiInspector, element: &IUIAutomationElement, control_type: &str) -> Vec<String> {
+    let selector = Selector { control_type: Some(control_type.to_string()), ..Selector::default() };
+    inspector
+        .find_in(element, TreeScope_Descendants, &selector)
+        .unwrap_or_default()
+        .iter()
+        .map(element_name)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn element_name(element: &IUIAutomationElement) -> String {
+    unsafe { element.CurrentName().unwrap_or(BSTR::from("")).to_string() }
+}
+
+fn label(name: &str) -> String {
+    name.replace('&', "").trim().to_lowercase()
+}
@@ -0,0 +1,219 @@
+use crate::inspector::UiElement;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+#[derive(Serialize, Debug)]
+pub struct PropertyChange {
+    pub property: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ElementChange {
+    pub id: String,
+    pub changes: Vec<PropertyChange>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AddedOrRemoved {
+    pub id: String,
+    pub element: UiElement,
+}
+
+/// Differences between two snapshots of the same scope. Added and removed entries
+/// are reported at the top-most differing element and carry its whole subtree.
+#[derive(Serialize, Debug, Default)]
+pub struct TreeDiff {
+    pub added: Vec<AddedOrRemoved>,
+    pub removed: Vec<AddedOrRemoved>,
+    pub changed: Vec<ElementChange>,
+}
+
+impl TreeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub fn diff_trees(old: &UiElement, new: &UiElement) -> TreeDiff {
+    let old_index = index_tree(old);
+    let new_index = index_tree(new);
+    let old_by_id: HashMap<&str, &UiElement> = old_index.iter().map(|node| (node.id.as_str(), node.element)).collect();
+    let new_by_id: HashMap<&str, &UiElement> = new_index.iter().map(|node| (node.id.as_str(), node.element)).collect();
+
+    let mut diff = TreeDiff::default();
+
+    for node in &new_index {
+        match old_by_id.get(node.id.as_str()) {
+            Some(previous) => {
+                let changes = property_changes(previous, node.element);
+                if !changes.is_empty() {
+                    diff.changed.push(ElementChange { id: node.id.clone(), changes });
+                }
+            }
+            None if node.parent.is_none_or(|parent| old_by_id.contains_key(new_index[parent].id.as_str())) => {
+                diff.added.push(AddedOrRemoved { id: node.id.clone(), element: node.element.clone() });
+            }
+            None => {}
+        }
+    }
+
+    for node in &old_index {
+        let parent_kept = node.parent.is_none_or(|parent| new_by_id.contains_key(old_index[parent].id.as_str()));
+        if !new_by_id.contains_key(node.id.as_str()) && parent_kept {
+            diff.removed.push(AddedOrRemoved { id: node.id.clone(), element: node.element.clone() });
+        }
+    }
+
+    diff
+}
+
+struct IndexedNode<'a> {
+    id: String,
+    /// Position of the parent node in the index.
+    parent: Option<usize>,
+    element: &'a UiElement,
+}
+
+/// Flattens the tree into identified nodes in document order.
+///
+/// An id is the path of segments from the root, each `type#automation_id` or
+/// `type"name"`, with a `[n]` suffix when siblings share the same segment.
+/// Bounds and state are deliberately left out so moved or toggled elements keep their id.
+fn index_tree(root: &UiElement) -> Vec<IndexedNode<'_>> {
+    let mut nodes = Vec::new();
+    index_element(root, segment(root), None, &mut nodes);
+    nodes
+}
+
+fn index_element<'a>(el: &'a UiElement, id: String, parent: Option<usize>, nodes: &mut Vec<IndexedNode<'a>>) {
+    let position = nodes.len();
+    nodes.push(IndexedNode { id: id.clone(), parent, element: el });
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for child in &el.children {
+        let base = segment(child);
+        let occurrence = seen.entry(base.clone()).or_insert(0);
+        let child_segment = if *occurrence == 0 { base } else { format!("{}[{}]", base, occurrence) };
+        *occurrence += 1;
+        index_element(child, format!("{}/{}", id, child_segment), Some(position), nodes);
+    }
+}
+
+fn segment(el: &UiElement) -> String {
+    let control_type = if el.control_type.is_empty() { "element" } else { &el.control_type };
+    if el.automation_id.is_empty() {
+        format!("{}{:?}", control_type, el.name)
+    } else {
+        format!("{}#{}", control_type, el.automation_id)
+    }
+}
+
+fn property_changes(old: &UiElement, new: &UiElement) -> Vec<PropertyChange> {
+    let old_props = own_properties(old);
+    let new_props = own_properties(new);
+
+    let mut keys: Vec<&String> = old_props.keys().chain(new_props.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let before = old_props.get(key).cloned().unwrap_or(Value::Null);
+            let after = new_props.get(key).cloned().unwrap_or(Value::Null);
+            (before != after).then(|| PropertyChange { property: key.clone(), old: before, new: after })
+        })
+        .collect()
+}
+
+fn own_properties(el: &UiElement) -> Map<String, Value> {
+    match serde_json::to_value(el.without_children()) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(control_type: &str, automation_id: &str, name: &str, children: Vec<UiElement>) -> UiElement {
+        UiElement {
+            control_type: control_type.to_string(),
+            automation_id: automation_id.to_string(),
+            name: name.to_string(),
+            children,
+            ..Default::default()
+        }
+    }
+
+    fn button(id: &str) -> UiElement {
+        element("button", id, id, Vec::new())
+    }
+
+    fn window(children: Vec<UiElement>) -> UiElement {
+        element("window", "main", "Main", children)
+    }
+
+    #[test]
+    fn identical_trees_have_no_diff() {
+        let tree = window(vec![button("ok"), button("cancel")]);
+        assert!(diff_trees(&tree, &tree.clone()).is_empty());
+    }
+
+    #[test]
+    fn reports_added_subtree_once_at_its_root() {
+        let old = window(vec![button("ok")]);
+        let new = window(vec![button("ok"), element("pane", "extra", "", vec![button("inner")])]);
+        let diff = diff_trees(&old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].id, "window#main/pane#extra");
+        assert_eq!(diff.added[0].element.children.len(), 1);
+        assert!(diff.removed.is_empty() && diff.changed.is_empty());
+    }
+
+    #[test]
+    fn reports_removed_element() {
+        let old = window(vec![button("ok"), button("cancel")]);
+        let new = window(vec![button("ok")]);
+        let diff = diff_trees(&old, &new);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].id, "window#main/button#cancel");
+        assert!(diff.added.is_empty() && diff.changed.is_empty());
+    }
+
+    #[test]
+    fn reports_property_changes_by_name() {
+        let old = window(vec![button("ok")]);
+        let mut changed = button("ok");
+        changed.name = "Confirm".to_string();
+        changed.rect = [10, 10, 50, 30];
+        let new = window(vec![changed]);
+
+        let diff = diff_trees(&old, &new);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].id, "window#main/button#ok");
+        let properties: Vec<&str> = diff.changed[0].changes.iter().map(|c| c.property.as_str()).collect();
+        assert_eq!(properties, ["name", "rect"]);
+        assert_eq!(diff.changed[0].changes[0].old, Value::from("ok"));
+        assert_eq!(diff.changed[0].changes[0].new, Value::from("Confirm"));
+    }
+
+    #[test]
+    fn reordered_siblings_keep_their_ids() {
+        let old = window(vec![button("ok"), button("cancel")]);
+        let new = window(vec![button("cancel"), button("ok")]);
+        assert!(diff_trees(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn unnamed_duplicates_are_told_apart_by_position() {
+        let old = window(vec![element("text", "", "Item", Vec::new()), element("text", "", "Item", Vec::new())]);
+        let new = window(vec![element("text", "", "Item", Vec::new())]);
+        let diff = diff_trees(&old, &new);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].id, "window#main/text\"Item\"[1]");
+    }
+}
@@ -0,0 +1,288 @@
+use windows::Win32::UI::Accessibility::*;
+use windows::Win32::System::Com::SAFEARRAY;
+use windows::core::{implement, Interface, BSTR, VARIANT};
+use anyhow::{Result, Context};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::Sender;
+use crate::inspector::UiInspector;
+
+const BUFFER_CAPACITY: usize = 500;
+/// Events waiting to be sent as notifications; while the client lags, newer events are only buffered.
+pub const NOTIFICATION_QUEUE: usize = 100;
+
+/// Properties whose changes are reported as `property_changed` events.
+const WATCHED_PROPERTIES: [(UIA_PROPERTY_ID, &str); 4] = [
+    (UIA_NamePropertyId, "Name"),
+    (UIA_IsEnabledPropertyId, "IsEnabled"),
+    (UIA_ValueValuePropertyId, "Value"),
+    (UIA_ToggleToggleStatePropertyId, "ToggleState"),
+];
+
+const STRUCTURE_CHANGES: [(StructureChangeType, &str); 6] = [
+    (StructureChangeType_ChildAdded, "child_added"),
+    (StructureChangeType_ChildRemoved, "child_removed"),
+    (StructureChangeType_ChildrenInvalidated, "children_invalidated"),
+    (StructureChangeType_ChildrenBulkAdded, "children_bulk_added"),
+    (StructureChangeType_ChildrenBulkRemoved, "children_bulk_removed"),
+    (StructureChangeType_ChildrenReordered, "children_reordered"),
+];
+
+const WINDOW_EVENTS: [(UIA_EVENT_ID, &str); 2] = [
+    (UIA_Window_WindowOpenedEventId, "window_opened"),
+    (UIA_Window_WindowClosedEventId, "window_closed"),
+];
+
+fn label<T: PartialEq>(table: &[(T, &'static str)], key: T) -> &'static str {
+    table.iter().find(|(k, _)| *k == key).map(|(_, name)| *name).unwrap_or("unknown")
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct UiEvent {
+    pub kind: String,
+    pub timestamp_ms: u64,
+    pub name: String,
+    pub control_type: String,
+    pub automation_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+}
+
+/// Ring buffer of the most recent UI events, shared with the `get_recent_events` tool.
+pub struct EventBuffer {
+    events: Mutex<VecDeque<UiEvent>>,
+}
+
+impl EventBuffer {
+    fn new() -> Self {
+        Self { events: Mutex::new(VecDeque::with_capacity(BUFFER_CAPACITY)) }
+    }
+
+    fn push(&self, event: UiEvent) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == BUFFER_CAPACITY {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// Returns up to `limit` newest events, oldest first, optionally filtered by kind and time.
+    pub fn recent(&self, limit: usize, kinds: &[String], since_ms: u64) -> Vec<UiEvent> {
+        let events = self.events.lock().unwrap();
+        let mut selected: Vec<UiEvent> = events
+            .iter()
+            .rev()
+            .filter(|e| e.timestamp_ms > since_ms)
+            .filter(|e| kinds.is_empty() || kinds.contains(&e.kind))
+            .take(limit)
+            .cloned()
+            .collect();
+        selected.reverse();
+        selected
+    }
+}
+
+#[implement(
+    IUIAutomationFocusChangedEventHandler,
+    IUIAutomationStructureChangedEventHandler,
+    IUIAutomationEventHandler,
+    IUIAutomationPropertyChangedEventHandler
+)]
+struct EventSink {
+    buffer: std::sync::Arc<EventBuffer>,
+    sender: Sender<UiEvent>,
+}
+
+impl EventSink {
+    /// Reads the properties UIA cached with the event, so no cross-process call is made per event.
+    fn record(&self, kind: &str, element: Option<&IUIAutomationElement>, detail: Option<serde_json::Value>) {
+        let (name, control_type, automation_id) = match element {
+            Some(el) => unsafe {
+                (
+                    el.CachedName().unwrap_or(BSTR::from("")).to_string(),
+                    el.CachedLocalizedControlType().unwrap_or(BSTR::from("")).to_string(),
+                    el.CachedAutomationId().unwrap_or(BSTR::from("")).to_string(),
+                )
+            },
+            None => Default::default(),
+        };
+
+        let event = UiEvent {
+            kind: kind.to_string(),
+            timestamp_ms: now_ms(),
+            name,
+            control_type,
+            automation_id,
+            detail,
+        };
+        self.buffer.push(event.clone());
+        let _ = self.sender.try_send(event);
+    }
+}
+
+impl IUIAutomationFocusChangedEventHandler_Impl for EventSink_Impl {
+    fn HandleFocusChangedEvent(&self, sender: Option<&IUIAutomationElement>) -> windows::core::Result<()> {
+        self.record("focus_changed", sender, None);
+        Ok(())
+    }
+}
+
+impl IUIAutomationStructureChangedEventHandler_Impl for EventSink_Impl {
+    fn HandleStructureChangedEvent(&self, sender: Option<&IUIAutomationElement>, changetype: StructureChangeType, _runtimeid: *const SAFEARRAY) -> windows::core::Result<()> {
+        let change = label(&STRUCTURE_CHANGES, changetype);
+        self.record("structure_changed", sender, Some(serde_json::json!({ "change": change })));
+        Ok(())
+    }
+}
+
+impl IUIAutomationEventHandler_Impl for EventSink_Impl {
+    fn HandleAutomationEvent(&self, sender: Option<&IUIAutomationElement>, eventid: UIA_EVENT_ID) -> windows::core::Result<()> {
+        self.record(label(&WINDOW_EVENTS, eventid), sender, None);
+        Ok(())
+    }
+}
+
+impl IUIAutomationPropertyChangedEventHandler_Impl for EventSink_Impl {
+    fn HandlePropertyChangedEvent(&self, sender: Option<&IUIAutomationElement>, propertyid: UIA_PROPERTY_ID, newvalue: &VARIANT) -> windows::core::Result<()> {
+        let property = label(&WATCHED_PROPERTIES, propertyid);
+        let detail = serde_json::json!({ "property": property, "value": newvalue.to_string() });
+        self.record("property_changed", sender, Some(detail));
+        Ok(())
+    }
+}
+
+/// Subscribes to UIA focus, structure, window and property events on the desktop.
+/// Events are buffered and pushed to `sender` while it has room; the subscriptions live as long as the monitor.
+pub struct EventMonitor {
+    automation: IUIAutomation,
+    buffer: std::sync::Arc<EventBuffer>,
+    forwarding: AtomicBool,
+}
+
+unsafe impl Send for EventMonitor {}
+unsafe impl Sync for EventMonitor {}
+
+impl EventMonitor {
+    pub fn start(inspector: &UiInspector, sender: Sender<UiEvent>) -> Result<Self> {
+        let automation = inspector.automation().clone();
+        let buffer = std::sync::Arc::new(EventBuffer::new());
+        let sink = EventSink { buffer: buffer.clone(), sender };
+
+        unsafe {
+            let root = automation.GetRootElement().context("Failed to get root element")?;
+            let request = automation.CreateCacheRequest().context("Failed to create cache request")?;
+            for id in [UIA_NamePropertyId, UIA_LocalizedControlTypePropertyId, UIA_AutomationIdPropertyId] {
+                request.AddProperty(id)?;
+            }
+
+            let focus_handler: IUIAutomationFocusChangedEventHandler = sink.into();
+            automation.AddFocusChangedEventHandler(&request, &focus_handler)
+                .context("Failed to subscribe to focus events")?;
+
+            // Only the desktop and top-level windows: subtree-wide subscriptions flood the buffer.
+            let top_level = TreeScope(TreeScope_Element.0 | TreeScope_Children.0);
+            let structure_handler: IUIAutomationStructureChangedEventHandler = focus_handler.cast()?;
+            automation.AddStructureChangedEventHandler(&root, top_level, &request, &structure_handler)
+                .context("Failed to subscribe to structure events")?;
+
+            let window_handler: IUIAutomationEventHandler = focus_handler.cast()?;
+            for (event, _) in WINDOW_EVENTS {
+                automation.AddAutomationEventHandler(event, &root, TreeScope_Subtree, &request, &window_handler)
+                    .context("Failed to subscribe to window events")?;
+            }
+
+            let property_handler: IUIAutomationPropertyChangedEventHandler = focus_handler.cast()?;
+            let properties: Vec<UIA_PROPERTY_ID> = WATCHED_PROPERTIES.iter().map(|(id, _)| *id).collect();
+            automation.AddPropertyChangedEventHandlerNativeArray(&root, top_level, &request, &property_handler, &properties)
+                .context("Failed to subscribe to property events")?;
+        }
+
+        Ok(Self { automation, buffer, forwarding: AtomicBool::new(false) })
+    }
+
+    pub fn buffer(&self) -> &EventBuffer {
+        &self.buffer
+    }
+
+    /// Events are only sent as notifications once the client has initialized the session.
+    pub fn set_forwarding(&self, enabled: bool) {
+        self.forwarding.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_forwarding(&self) -> bool {
+        self.forwarding.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for EventMonitor {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.automation.RemoveAllEventHandlers();
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: &str, timestamp_ms: u64) -> UiEvent {
+        UiEvent {
+            kind: kind.to_string(),
+            timestamp_ms,
+            name: String::new(),
+            control_type: String::new(),
+            automation_id: String::new(),
+            detail: None,
+        }
+    }
+
+    fn timestamps(events: &[UiEvent]) -> Vec<u64> {
+        events.iter().map(|e| e.timestamp_ms).collect()
+    }
+
+    #[test]
+    fn evicts_the_oldest_events_at_capacity() {
+        let buffer = EventBuffer::new();
+        for timestamp in 1..=BUFFER_CAPACITY as u64 + 2 {
+            buffer.push(event("focus_changed", timestamp));
+        }
+        let events = buffer.recent(usize::MAX, &[], 0);
+        assert_eq!(events.len(), BUFFER_CAPACITY);
+        assert_eq!(events[0].timestamp_ms, 3);
+        assert_eq!(events[BUFFER_CAPACITY - 1].timestamp_ms, BUFFER_CAPACITY as u64 + 2);
+    }
+
+    #[test]
+    fn recent_returns_the_newest_events_oldest_first() {
+        let buffer = EventBuffer::new();
+        for timestamp in 1..=5 {
+            buffer.push(event("focus_changed", timestamp));
+        }
+        assert_eq!(timestamps(&buffer.recent(3, &[], 0)), [3, 4, 5]);
+        assert!(buffer.recent(0, &[], 0).is_empty());
+    }
+
+    #[test]
+    fn recent_filters_by_kind_and_time() {
+        let buffer = EventBuffer::new();
+        buffer.push(event("focus_changed", 10));
+        buffer.push(event("window_opened", 20));
+        buffer.push(event("property_changed", 30));
+        buffer.push(event("window_opened", 40));
+
+        let kinds = vec!["window_opened".to_string(), "property_changed".to_string()];
+        assert_eq!(timestamps(&buffer.recent(10, &kinds, 0)), [20, 30, 40]);
+        // `since_ms` is exclusive.
+        assert_eq!(timestamps(&buffer.recent(10, &[], 20)), [30, 40]);
+        assert_eq!(timestamps(&buffer.recent(1, &kinds, 20)), [40]);
+        assert!(buffer.recent(10, &["structure_changed".to_string()], 0).is_empty());
+    }
+}
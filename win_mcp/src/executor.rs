@@ -68,20 +68,14 @@ impl HardwareExecutor {
         Ok(())
     }
 
+    /// Sends the characters `can_type` accepts and skips the rest.
     pub fn type_text(&self, text: &str) -> Result<()> {
-        for c in text.chars() {
-            if c.is_ascii_lowercase() {
-                let scan_code = (c as u8 - b'a' + 0x1E) as u16;
+        for c in text.chars().filter(|c| can_type(*c)) {
+            let Some(code) = scan_code(c) else { continue };
+            for state in [KeyState::DOWN, KeyState::UP] {
                 let stroke = Stroke::Keyboard {
-                    code: scan_code.try_into().unwrap_or(interception::ScanCode::Esc),
-                    state: KeyState::DOWN,
-                    information: 0,
-                };
-                self.context.send(1, &[stroke]);
-                
-                let stroke = Stroke::Keyboard {
-                    code: scan_code.try_into().unwrap_or(interception::ScanCode::Esc),
-                    state: KeyState::UP,
+                    code: code.try_into().map_err(|_| anyhow!("Unsupported scan code 0x{:X}", code))?,
+                    state,
                     information: 0,
                 };
                 self.context.send(1, &[stroke]);
@@ -89,4 +83,86 @@ impl HardwareExecutor {
         }
         Ok(())
     }
+
+    /// Presses `codes` in order and releases them in reverse, e.g. `[ALT, F]` for Alt+F.
+    pub fn press_keys(&self, codes: &[u16]) -> Result<()> {
+        let stroke = |code: u16, state: KeyState| -> Result<Stroke> {
+            Ok(Stroke::Keyboard {
+                code: code.try_into().map_err(|_| anyhow!("Unsupported scan code 0x{:X}", code))?,
+                state,
+                information: 0,
+            })
+        };
+
+        for &code in codes {
+            self.context.send(1, &[stroke(code, KeyState::DOWN)?]);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        for &code in codes.iter().rev() {
+            self.context.send(1, &[stroke(code, KeyState::UP)?]);
+        }
+        Ok(())
+    }
+}
+
+pub const SCAN_ALT: u16 = 0x38;
+
+/// Whether `type_text` can send `c`: only lowercase ASCII letters, which need no Shift.
+/// Keys are sent by their US-layout position, so other layouts may produce other letters.
+pub fn can_type(c: char) -> bool {
+    c.is_ascii_lowercase()
+}
+
+/// Set 1 scan code of a letter or digit on a US layout.
+pub fn scan_code(c: char) -> Option<u16> {
+    const LETTERS: [u16; 26] = [
+        0x1E, 0x30, 0x2E, 0x20, 0x12, 0x21, 0x22, 0x23, 0x17, 0x24, 0x25, 0x26, 0x32,
+        0x31, 0x18, 0x19, 0x10, 0x13, 0x1F, 0x14, 0x16, 0x2F, 0x11, 0x2D, 0x15, 0x2C,
+    ];
+    match c.to_ascii_lowercase() {
+        c @ 'a'..='z' => Some(LETTERS[(c as u8 - b'a') as usize]),
+        '0' => Some(0x0B),
+        c @ '1'..='9' => Some((c as u8 - b'1') as u16 + 0x02),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_follow_the_keyboard_not_the_alphabet() {
+        assert_eq!(scan_code('a'), Some(0x1E));
+        assert_eq!(scan_code('b'), Some(0x30));
+        assert_eq!(scan_code('q'), Some(0x10));
+        assert_eq!(scan_code('s'), Some(0x1F));
+        assert_eq!(scan_code('z'), Some(0x2C));
+        assert_eq!(scan_code('Z'), Some(0x2C));
+    }
+
+    #[test]
+    fn letter_scan_codes_are_distinct() {
+        let mut codes: Vec<u16> = ('a'..='z').filter_map(scan_code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), 26);
+    }
+
+    #[test]
+    fn digits_and_other_characters() {
+        assert_eq!(scan_code('1'), Some(0x02));
+        assert_eq!(scan_code('9'), Some(0x0A));
+        assert_eq!(scan_code('0'), Some(0x0B));
+        assert_eq!(scan_code(' '), None);
+        assert_eq!(scan_code('é'), None);
+    }
+
+    #[test]
+    fn only_lowercase_letters_are_typeable() {
+        assert!(can_type('b'));
+        assert!(!can_type('B'));
+        assert!(!can_type('1'));
+        assert!(!can_type(' '));
+    }
 }
@@ -1,16 +1,327 @@
 use windows::Win32::UI::Accessibility::*;
 use windows::Win32::System::Com::*;
-use windows::core::BSTR;
-use anyhow::{Result, Context};
+use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+use windows::Win32::Foundation::HWND;
+use windows::core::{BSTR, VARIANT, Interface};
+use anyhow::{Result, Context, anyhow};
 use serde::{Serialize, Deserialize};
+use std::time::{Duration, Instant};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct UiElement {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub control_type: String,
+    /// Language-independent control type ("Button", "ListItem"); `control_type` is localized.
+    #[serde(skip)]
+    pub type_name: &'static str,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub automation_id: String,
     pub rect: [i32; 4], // [left, top, right, bottom]
+
+    // Optional properties, fetched only when requested through a `PropertySet`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_offscreen: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_keyboard_focus: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_keyboard_focusable: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub framework_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_window_handle: Option<isize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accelerator_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<UiElement>,
+    /// Children left out because of the node, child or time budget.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub more_children: Option<usize>,
+}
+
+impl UiElement {
+    /// Copy of this element's own properties, without its subtree.
+    pub fn without_children(&self) -> UiElement {
+        UiElement { children: Vec::new(), more_children: None, ..self.clone() }
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property {
+    IsEnabled,
+    IsOffscreen,
+    HasKeyboardFocus,
+    IsKeyboardFocusable,
+    Value,
+    ClassName,
+    FrameworkId,
+    ProcessId,
+    NativeWindowHandle,
+    HelpText,
+    AcceleratorKey,
+    AccessKey,
+}
+
+impl Property {
+    pub const ALL: [Property; 12] = [
+        Property::IsEnabled,
+        Property::IsOffscreen,
+        Property::HasKeyboardFocus,
+        Property::IsKeyboardFocusable,
+        Property::Value,
+        Property::ClassName,
+        Property::FrameworkId,
+        Property::ProcessId,
+        Property::NativeWindowHandle,
+        Property::HelpText,
+        Property::AcceleratorKey,
+        Property::AccessKey,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Property::IsEnabled => "is_enabled",
+            Property::IsOffscreen => "is_offscreen",
+            Property::HasKeyboardFocus => "has_keyboard_focus",
+            Property::IsKeyboardFocusable => "is_keyboard_focusable",
+            Property::Value => "value",
+            Property::ClassName => "class_name",
+            Property::FrameworkId => "framework_id",
+            Property::ProcessId => "process_id",
+            Property::NativeWindowHandle => "native_window_handle",
+            Property::HelpText => "help_text",
+            Property::AcceleratorKey => "accelerator_key",
+            Property::AccessKey => "access_key",
+        }
+    }
+
+    fn property_id(self) -> UIA_PROPERTY_ID {
+        match self {
+            Property::IsEnabled => UIA_IsEnabledPropertyId,
+            Property::IsOffscreen => UIA_IsOffscreenPropertyId,
+            Property::HasKeyboardFocus => UIA_HasKeyboardFocusPropertyId,
+            Property::IsKeyboardFocusable => UIA_IsKeyboardFocusablePropertyId,
+            Property::Value => UIA_ValueValuePropertyId,
+            Property::ClassName => UIA_ClassNamePropertyId,
+            Property::FrameworkId => UIA_FrameworkIdPropertyId,
+            Property::ProcessId => UIA_ProcessIdPropertyId,
+            Property::NativeWindowHandle => UIA_NativeWindowHandlePropertyId,
+            Property::HelpText => UIA_HelpTextPropertyId,
+            Property::AcceleratorKey => UIA_AcceleratorKeyPropertyId,
+            Property::AccessKey => UIA_AccessKeyPropertyId,
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+/// Optional properties to fetch in addition to name, type, id and bounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PropertySet(u32);
+
+impl PropertySet {
+    pub const BASIC: PropertySet = PropertySet(0);
+
+    /// State that decides whether an element can be acted upon.
+    pub fn interactive() -> Self {
+        Self::from_properties(&[
+            Property::IsEnabled,
+            Property::IsOffscreen,
+            Property::HasKeyboardFocus,
+            Property::IsKeyboardFocusable,
+            Property::Value,
+        ])
+    }
+
+    pub fn full() -> Self {
+        Self::from_properties(&Property::ALL)
+    }
+
+    pub fn from_properties(properties: &[Property]) -> Self {
+        PropertySet(properties.iter().fold(0, |bits, p| bits | p.bit()))
+    }
+
+    pub fn contains(self, property: Property) -> bool {
+        self.0 & property.bit() != 0
+    }
+
+    /// Accepts a preset name ("basic", "interactive", "full") or a list of property names.
+    pub fn parse(value: &serde_json::Value) -> Result<Self> {
+        match value {
+            serde_json::Value::Null => Ok(Self::BASIC),
+            serde_json::Value::String(preset) => match preset.as_str() {
+                "basic" => Ok(Self::BASIC),
+                "interactive" => Ok(Self::interactive()),
+                "full" => Ok(Self::full()),
+                other => Err(anyhow!("Unknown property set: {}", other)),
+            },
+            serde_json::Value::Array(names) => {
+                let mut properties = Vec::new();
+                for name in names {
+                    let name = name.as_str().ok_or_else(|| anyhow!("Property names must be strings"))?;
+                    let property = Property::ALL.iter().find(|p| p.name() == name)
+                        .ok_or_else(|| anyhow!("Unknown property: {}", name))?;
+                    properties.push(*property);
+                }
+                Ok(Self::from_properties(&properties))
+            }
+            _ => Err(anyhow!("properties must be a preset name or a list of property names")),
+        }
+    }
+}
+
+/// Describes the element to look for. Every field that is set must match.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Selector {
+    pub name: Option<String>,
+    pub name_contains: Option<String>,
+    pub control_type: Option<String>, // UIA name ("Button") or localized type ("button")
+    pub automation_id: Option<String>,
+    pub class_name: Option<String>,
+}
+
+impl Selector {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.name_contains.is_none()
+            && self.control_type.is_none()
+            && self.automation_id.is_none()
+            && self.class_name.is_none()
+    }
+}
+
+/// UIA's own default, restored after a bounded traversal when the previous value is unreadable.
+const DEFAULT_TRANSACTION_TIMEOUT_MS: u32 = 20_000;
+
+/// How `get_ui_tree` talks to UIA.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraversalMode {
+    /// One `FindAllBuildCache` per node returns children with their properties in bulk.
+    Cached,
+    /// One `Current*` call per property plus a `FindAll` per node.
+    Live,
+}
+
+impl TraversalMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "cached" => Some(Self::Cached),
+            "live" => Some(Self::Live),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cached => "cached",
+            Self::Live => "live",
+        }
+    }
+}
+
+pub struct TraversalOptions {
+    /// Title fragment of the top-level window to start from; the desktop when `None`.
+    pub window: Option<String>,
+    pub max_depth: usize,
+    pub properties: PropertySet,
+    pub mode: TraversalMode,
+    pub max_nodes: usize,
+    pub max_children: usize,
+    pub timeout: Duration,
+}
+
+/// Timing metrics reported alongside the tree so both traversal modes can be compared.
+#[derive(Serialize, Debug)]
+pub struct TraversalStats {
+    pub mode: &'static str,
+    pub nodes: usize,
+    pub elapsed_ms: u64,
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncation_reason: Option<&'static str>,
+}
+
+/// Node and time limits shared by every level of one traversal.
+struct Budget {
+    nodes: usize,
+    max_nodes: usize,
+    max_children: usize,
+    deadline: Instant,
+    truncation_reason: Option<&'static str>,
+    /// When set, each cross-process call is bounded by the time left before `deadline`.
+    transaction: Option<IUIAutomation2>,
+}
+
+impl Budget {
+    fn new(options: &TraversalOptions) -> Self {
+        Self {
+            nodes: 0,
+            max_nodes: options.max_nodes,
+            max_children: options.max_children,
+            deadline: Instant::now() + options.timeout,
+            truncation_reason: None,
+            transaction: None,
+        }
+    }
+
+    fn unlimited() -> Self {
+        Self {
+            nodes: 0,
+            max_nodes: usize::MAX,
+            max_children: usize::MAX,
+            deadline: Instant::now() + Duration::from_secs(3600),
+            truncation_reason: None,
+            transaction: None,
+        }
+    }
+
+    /// Returns false (and records why) once no more nodes may be visited.
+    fn admit(&mut self) -> bool {
+        if self.truncation_reason.is_some() {
+            return false;
+        }
+        if self.nodes >= self.max_nodes {
+            self.truncation_reason = Some("max_nodes");
+            return false;
+        }
+        if Instant::now() >= self.deadline {
+            self.truncation_reason = Some("deadline");
+            return false;
+        }
+        true
+    }
+
+    fn expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// Caps UIA's transaction timeout at the remaining time, so a hung provider cannot
+    /// hold the next call past the deadline.
+    fn bound_transaction(&self) {
+        if let Some(automation2) = &self.transaction {
+            let remaining = self.deadline.saturating_duration_since(Instant::now());
+            let timeout_ms = remaining.as_millis().clamp(1, u32::MAX as u128) as u32;
+            unsafe { let _ = automation2.SetTransactionTimeout(timeout_ms); }
+        }
+    }
 }
 
 pub struct UiInspector {
@@ -24,49 +335,626 @@ impl UiInspector {
     pub fn new() -> Result<Self> {
         unsafe {
             let _ = CoInitializeEx(None, COINIT_MULTITHREADED).ok();
-            let automation: IUIAutomation = CoCreateInstance(&CUIAutomation, None, CLSCTX_ALL)
+            // CUIAutomation8 exposes IUIAutomation2 timeouts; fall back for older systems.
+            let automation: IUIAutomation = CoCreateInstance(&CUIAutomation8, None, CLSCTX_ALL)
+                .or_else(|_| CoCreateInstance(&CUIAutomation, None, CLSCTX_ALL))
                 .context("Failed to create UI Automation instance")?;
             Ok(Self { automation })
         }
     }
 
-    pub fn get_ui_tree(&self, max_depth: usize) -> Result<UiElement> {
+    pub fn automation(&self) -> &IUIAutomation {
+        &self.automation
+    }
+
+    /// Walks the desktop tree within the node, child and time limits of `options`.
+    /// When a limit is hit the partial tree is returned and the stats say why.
+    pub fn get_ui_tree(&self, options: &TraversalOptions) -> Result<(UiElement, TraversalStats)> {
+        let started = Instant::now();
+        let mut budget = Budget::new(options);
+
+        let tree = self.bounded(&mut budget, |budget| unsafe {
+            match options.mode {
+                TraversalMode::Live => {
+                    let root = self.traversal_root(options)?;
+                    self.traverse_element(&root, 0, options.max_depth, options.properties, budget)
+                }
+                TraversalMode::Cached => {
+                    let request = self.cache_request(options.properties)?;
+                    let root = self.traversal_root(options)?
+                        .BuildUpdatedCache(&request)
+                        .context("Failed to cache root element")?;
+                    self.traverse_cached(&root, &request, 0, options, budget)
+                }
+            }
+        });
+
+        let stats = TraversalStats {
+            mode: options.mode.name(),
+            nodes: budget.nodes,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            truncated: budget.truncation_reason.is_some(),
+            truncation_reason: budget.truncation_reason,
+        };
+        Ok((tree?, stats))
+    }
+
+    /// Runs `f` with each UIA call it makes bounded by `timeout`.
+    pub fn within<T>(&self, timeout: Duration, f: impl FnOnce() -> T) -> T {
+        let mut budget = Budget { deadline: Instant::now() + timeout, ..Budget::unlimited() };
+        self.bounded(&mut budget, |_| f())
+    }
+
+    /// Runs `f` with each UIA call bounded by `budget`'s deadline. A hung provider would otherwise
+    /// block FindAll indefinitely. The timeout is shared by the whole instance, so the previous
+    /// value is put back afterwards.
+    fn bounded<T>(&self, budget: &mut Budget, f: impl FnOnce(&mut Budget) -> T) -> T {
+        let automation2: Option<IUIAutomation2> = self.automation.cast().ok();
+        let previous_timeout = automation2.as_ref()
+            .and_then(|automation2| unsafe { automation2.TransactionTimeout().ok() })
+            .unwrap_or(DEFAULT_TRANSACTION_TIMEOUT_MS);
+        budget.transaction = automation2.clone();
+        budget.bound_transaction();
+
+        let result = f(budget);
+
+        if let Some(automation2) = &automation2 {
+            unsafe { let _ = automation2.SetTransactionTimeout(previous_timeout); }
+        }
+        result
+    }
+
+    fn traversal_root(&self, options: &TraversalOptions) -> Result<IUIAutomationElement> {
+        match &options.window {
+            Some(title) => self.find_window(title)?.ok_or_else(|| anyhow!("Window not found: {}", title)),
+            None => unsafe { self.automation.GetRootElement().context("Failed to get root element") },
+        }
+    }
+
+    /// Returns all properties of a single element without its children.
+    pub fn describe(&self, element: &IUIAutomationElement) -> Result<UiElement> {
+        self.traverse_element(element, 0, 0, PropertySet::full(), &mut Budget::unlimited())
+    }
+
+    pub fn focused(&self) -> Result<IUIAutomationElement> {
+        unsafe { self.automation.GetFocusedElement().context("Failed to get focused element") }
+    }
+
+    /// The top-level window that currently receives input.
+    pub fn foreground_window(&self) -> Result<IUIAutomationElement> {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_invalid() {
+                return Err(anyhow!("No foreground window"));
+            }
+            self.automation.ElementFromHandle(hwnd).context("Failed to get foreground window element")
+        }
+    }
+
+    pub fn element_from_handle(&self, handle: u64) -> Result<IUIAutomationElement> {
+        unsafe {
+            self.automation.ElementFromHandle(HWND(handle as isize as *mut _))
+                .with_context(|| format!("Failed to get element for window {}", handle))
+        }
+    }
+
+    /// Parent in the control view; `None` at the desktop root.
+    pub fn parent(&self, element: &IUIAutomationElement) -> Option<IUIAutomationElement> {
+        unsafe {
+            let walker = self.automation.ControlViewWalker().ok()?;
+            walker.GetParentElement(element).ok()
+        }
+    }
+
+    /// Ancestors of `element` from its parent up to (and including) the desktop root.
+    pub fn ancestors(&self, element: &IUIAutomationElement) -> Vec<IUIAutomationElement> {
+        let mut chain = Vec::new();
+        let mut current = self.parent(element);
+        while let Some(ancestor) = current {
+            current = self.parent(&ancestor);
+            chain.push(ancestor);
+        }
+        chain
+    }
+
+    pub fn set_focus(&self, element: &IUIAutomationElement) -> Result<()> {
+        unsafe { element.SetFocus().context("The element refused keyboard focus") }
+    }
+
+    /// Whether `element` itself satisfies every field of `selector`.
+    pub fn matches(&self, element: &IUIAutomationElement, selector: &Selector) -> Result<bool> {
+        let condition = self.selector_condition(selector)?;
+        let matched = unsafe { element.FindFirst(TreeScope_Element, &condition) }.is_ok();
+        Ok(matched && self.matches_unindexed(element, selector))
+    }
+
+    /// Activates an element through InvokePattern, SelectionItemPattern or the
+    /// legacy default action, whichever it supports first.
+    pub fn invoke(&self, element: &IUIAutomationElement) -> Result<()> {
+        unsafe {
+            if let Ok(pattern) = element.GetCurrentPatternAs::<IUIAutomationInvokePattern>(UIA_InvokePatternId) {
+                return pattern.Invoke().context("Invoke failed");
+            }
+            if let Ok(pattern) = element.GetCurrentPatternAs::<IUIAutomationSelectionItemPattern>(UIA_SelectionItemPatternId) {
+                return pattern.Select().context("Select failed");
+            }
+            if let Ok(pattern) = element.GetCurrentPatternAs::<IUIAutomationLegacyIAccessiblePattern>(UIA_LegacyIAccessiblePatternId) {
+                return pattern.DoDefaultAction().context("Default action failed");
+            }
+        }
+        Err(anyhow!("Element supports neither Invoke, SelectionItem nor a default action"))
+    }
+
+    pub fn set_value(&self, element: &IUIAutomationElement, value: &str) -> Result<()> {
+        unsafe {
+            let pattern = element.GetCurrentPatternAs::<IUIAutomationValuePattern>(UIA_ValuePatternId)
+                .context("Element does not support ValuePattern")?;
+            pattern.SetValue(&BSTR::from(value)).context("SetValue failed")
+        }
+    }
+
+    pub fn is_enabled(&self, element: &IUIAutomationElement) -> bool {
+        unsafe { element.CurrentIsEnabled().map(|b| b.as_bool()).unwrap_or(false) }
+    }
+
+    pub fn process_id(&self, element: &IUIAutomationElement) -> Result<u32> {
+        unsafe { element.CurrentProcessId().map(|pid| pid as u32).context("Failed to read process id") }
+    }
+
+    pub fn find_first(&self, selector: &Selector) -> Result<Option<IUIAutomationElement>> {
+        Ok(self.find_all(selector)?.into_iter().next())
+    }
+
+    /// Searches the whole desktop for elements matching `selector`.
+    pub fn find_all(&self, selector: &Selector) -> Result<Vec<IUIAutomationElement>> {
+        let root = unsafe { self.automation.GetRootElement().context("Failed to get root element")? };
+        self.find_in(&root, TreeScope_Descendants, selector)
+    }
+
+    /// Searches `scope` of `element` for elements matching `selector`.
+    pub fn find_in(&self, element: &IUIAutomationElement, scope: TreeScope, selector: &Selector) -> Result<Vec<IUIAutomationElement>> {
+        unsafe {
+            let condition = self.selector_condition(selector)?;
+            let found = element.FindAll(scope, &condition).context("Failed to search elements")?;
+            let count = found.Length().unwrap_or(0);
+
+            let mut matches = Vec::new();
+            for i in 0..count {
+                if let Ok(element) = found.GetElement(i)
+                    && self.matches_unindexed(&element, selector)
+                {
+                    matches.push(element);
+                }
+            }
+            Ok(matches)
+        }
+    }
+
+    /// Finds a top-level window whose title contains `title` (case-insensitive).
+    pub fn find_window(&self, title: &str) -> Result<Option<IUIAutomationElement>> {
         unsafe {
             let root = self.automation.GetRootElement().context("Failed to get root element")?;
-            self.traverse_element(&root, 0, max_depth)
+            let condition = self.automation.CreateTrueCondition().context("Failed to create condition")?;
+            let windows = root.FindAll(TreeScope_Children, &condition).context("Failed to list windows")?;
+            let count = windows.Length().unwrap_or(0);
+            let needle = title.to_lowercase();
+
+            for i in 0..count {
+                if let Ok(window) = windows.GetElement(i) {
+                    let name = window.CurrentName().unwrap_or(BSTR::from("")).to_string();
+                    if name.to_lowercase().contains(&needle) {
+                        return Ok(Some(window));
+                    }
+                }
+            }
+            Ok(None)
+        }
+    }
+
+    /// Builds a UIA condition from the selector fields UIA can match natively.
+    fn selector_condition(&self, selector: &Selector) -> Result<IUIAutomationCondition> {
+        unsafe {
+            let mut condition = self.automation.CreateTrueCondition().context("Failed to create condition")?;
+            let mut properties: Vec<(UIA_PROPERTY_ID, VARIANT)> = Vec::new();
+
+            if let Some(name) = &selector.name {
+                properties.push((UIA_NamePropertyId, VARIANT::from(name.as_str())));
+            }
+            if let Some(id) = &selector.automation_id {
+                properties.push((UIA_AutomationIdPropertyId, VARIANT::from(id.as_str())));
+            }
+            if let Some(class) = &selector.class_name {
+                properties.push((UIA_ClassNamePropertyId, VARIANT::from(class.as_str())));
+            }
+            if let Some(type_id) = selector.control_type.as_deref().and_then(control_type_id) {
+                properties.push((UIA_ControlTypePropertyId, VARIANT::from(type_id.0)));
+            }
+
+            for (property, value) in properties {
+                let next = self.automation.CreatePropertyCondition(property, &value).context("Failed to create property condition")?;
+                condition = self.automation.CreateAndCondition(&condition, &next).context("Failed to combine conditions")?;
+            }
+            Ok(condition)
+        }
+    }
+
+    /// Checks the selector fields that cannot be expressed as UIA conditions.
+    fn matches_unindexed(&self, element: &IUIAutomationElement, selector: &Selector) -> bool {
+        unsafe {
+            if let Some(fragment) = &selector.name_contains {
+                let name = element.CurrentName().unwrap_or(BSTR::from("")).to_string();
+                if !name.to_lowercase().contains(&fragment.to_lowercase()) {
+                    return false;
+                }
+            }
+            if let Some(control_type) = &selector.control_type
+                && control_type_id(control_type).is_none()
+            {
+                let localized = element.CurrentLocalizedControlType().unwrap_or(BSTR::from("")).to_string();
+                if !localized.eq_ignore_ascii_case(control_type) {
+                    return false;
+                }
+            }
+            true
         }
     }
 
-    fn traverse_element(&self, element: &IUIAutomationElement, depth: usize, max_depth: usize) -> Result<UiElement> {
+    /// Fills in the optional properties selected by `properties`.
+    fn read_properties(&self, element: &IUIAutomationElement, properties: PropertySet, ui_el: &mut UiElement) {
+        unsafe {
+            if properties.contains(Property::IsEnabled) {
+                ui_el.is_enabled = element.CurrentIsEnabled().ok().map(|b| b.as_bool());
+            }
+            if properties.contains(Property::IsOffscreen) {
+                ui_el.is_offscreen = element.CurrentIsOffscreen().ok().map(|b| b.as_bool());
+            }
+            if properties.contains(Property::HasKeyboardFocus) {
+                ui_el.has_keyboard_focus = element.CurrentHasKeyboardFocus().ok().map(|b| b.as_bool());
+            }
+            if properties.contains(Property::IsKeyboardFocusable) {
+                ui_el.is_keyboard_focusable = element.CurrentIsKeyboardFocusable().ok().map(|b| b.as_bool());
+            }
+            if properties.contains(Property::Value) {
+                ui_el.value = element
+                    .GetCurrentPatternAs::<IUIAutomationValuePattern>(UIA_ValuePatternId)
+                    .and_then(|pattern| pattern.CurrentValue())
+                    .ok()
+                    .and_then(non_empty);
+            }
+            if properties.contains(Property::ClassName) {
+                ui_el.class_name = element.CurrentClassName().ok().and_then(non_empty);
+            }
+            if properties.contains(Property::FrameworkId) {
+                ui_el.framework_id = element.CurrentFrameworkId().ok().and_then(non_empty);
+            }
+            if properties.contains(Property::ProcessId) {
+                ui_el.process_id = element.CurrentProcessId().ok().map(|pid| pid as u32);
+            }
+            if properties.contains(Property::NativeWindowHandle) {
+                ui_el.native_window_handle = element.CurrentNativeWindowHandle().ok()
+                    .map(|hwnd| hwnd.0 as isize)
+                    .filter(|&handle| handle != 0);
+            }
+            if properties.contains(Property::HelpText) {
+                ui_el.help_text = element.CurrentHelpText().ok().and_then(non_empty);
+            }
+            if properties.contains(Property::AcceleratorKey) {
+                ui_el.accelerator_key = element.CurrentAcceleratorKey().ok().and_then(non_empty);
+            }
+            if properties.contains(Property::AccessKey) {
+                ui_el.access_key = element.CurrentAccessKey().ok().and_then(non_empty);
+            }
+        }
+    }
+
+    fn traverse_element(&self, element: &IUIAutomationElement, depth: usize, max_depth: usize, properties: PropertySet, budget: &mut Budget) -> Result<UiElement> {
+        budget.nodes += 1;
         unsafe {
             let name = element.CurrentName().unwrap_or(BSTR::from("")).to_string();
             let control_type = element.CurrentLocalizedControlType().unwrap_or(BSTR::from("")).to_string();
+            let type_name = element.CurrentControlType().map_or("", control_type_name);
             let automation_id = element.CurrentAutomationId().unwrap_or(BSTR::from("")).to_string();
             let rect = element.CurrentBoundingRectangle().unwrap_or_default();
 
             let mut ui_el = UiElement {
                 name,
                 control_type,
+                type_name,
                 automation_id,
                 rect: [rect.left, rect.top, rect.right, rect.bottom],
-                children: Vec::new(),
+                ..Default::default()
             };
+            self.read_properties(element, properties, &mut ui_el);
 
-            if depth < max_depth {
+            if depth < max_depth && budget.admit() {
                 let condition = self.automation.CreateTrueCondition().context("Failed to create condition")?;
-                let children = element.FindAll(TreeScope_Children, &condition).context("Failed to find children")?;
-                let count = children.Length().unwrap_or(0);
-
-                for i in 0..count {
-                    if let Ok(child) = children.GetElement(i) {
-                        if let Ok(child_tree) = self.traverse_element(&child, depth + 1, max_depth) {
-                            ui_el.children.push(child_tree);
-                        }
-                    }
+                budget.bound_transaction();
+                match element.FindAll(TreeScope_Children, &condition) {
+                    Ok(children) => visit_children(&children, &mut ui_el, budget, |child, budget| {
+                        self.traverse_element(child, depth + 1, max_depth, properties, budget)
+                    }),
+                    Err(_) if budget.expired() => budget.truncation_reason = Some("deadline"),
+                    Err(e) => return Err(e).context("Failed to find children"),
                 }
             }
 
             Ok(ui_el)
         }
     }
+
+    /// Enabled, on-screen elements under `root` that can be clicked or typed into,
+    /// in document order, with basic properties only.
+    pub fn interactive_elements(&self, root: &IUIAutomationElement, limit: usize) -> Result<Vec<UiElement>> {
+        unsafe {
+            let mut types: Option<IUIAutomationCondition> = None;
+            for type_id in INTERACTIVE_CONTROL_TYPES {
+                let next = self.automation.CreatePropertyCondition(UIA_ControlTypePropertyId, &VARIANT::from(type_id.0))?;
+                types = Some(match types {
+                    Some(previous) => self.automation.CreateOrCondition(&previous, &next)?,
+                    None => next,
+                });
+            }
+            let types = types.context("No interactive control types")?;
+            let visible = self.automation.CreatePropertyCondition(UIA_IsOffscreenPropertyId, &VARIANT::from(false))?;
+            let enabled = self.automation.CreatePropertyCondition(UIA_IsEnabledPropertyId, &VARIANT::from(true))?;
+            let condition = self.automation.CreateAndCondition(&self.automation.CreateAndCondition(&types, &visible)?, &enabled)?;
+
+            let request = self.cache_request(PropertySet::BASIC)?;
+            let found = root.FindAllBuildCache(TreeScope_Descendants, &condition, &request)
+                .context("Failed to search interactive elements")?;
+            let count = found.Length().unwrap_or(0).max(0) as usize;
+
+            Ok((0..count)
+                .filter_map(|i| found.GetElement(i as i32).ok())
+                .map(|element| cached_element(&element))
+                .filter(|el| el.rect[2] > el.rect[0] && el.rect[3] > el.rect[1])
+                .take(limit)
+                .collect())
+        }
+    }
+
+    /// On-screen password fields inside the given top-level windows, with basic properties only.
+    /// The whole search must finish within `timeout`; a provider that does not answer fails it.
+    pub fn password_fields(&self, windows: &[u64], timeout: Duration) -> Result<Vec<UiElement>> {
+        let mut budget = Budget { deadline: Instant::now() + timeout, ..Budget::unlimited() };
+        self.bounded(&mut budget, |budget| unsafe {
+            let password = self.automation.CreatePropertyCondition(UIA_IsPasswordPropertyId, &VARIANT::from(true))?;
+            let visible = self.automation.CreatePropertyCondition(UIA_IsOffscreenPropertyId, &VARIANT::from(false))?;
+            let condition = self.automation.CreateAndCondition(&password, &visible)?;
+            let request = self.cache_request(PropertySet::BASIC)?;
+
+            let mut fields = Vec::new();
+            for &handle in windows {
+                if budget.expired() {
+                    return Err(anyhow!("Password field search timed out"));
+                }
+                // A window that closed since it was listed has nothing left to hide.
+                let Ok(root) = self.element_from_handle(handle) else { continue };
+                budget.bound_transaction();
+                let found = root.FindAllBuildCache(TreeScope_Descendants, &condition, &request)
+                    .context("Failed to search password fields")?;
+                let count = found.Length().unwrap_or(0).max(0) as usize;
+                fields.extend((0..count)
+                    .filter_map(|i| found.GetElement(i as i32).ok())
+                    .map(|element| cached_element(&element))
+                    .filter(|el| el.rect[2] > el.rect[0] && el.rect[3] > el.rect[1]));
+            }
+            Ok(fields)
+        })
+    }
+
+    /// Cache request for the basic properties plus everything selected in `properties`.
+    fn cache_request(&self, properties: PropertySet) -> Result<IUIAutomationCacheRequest> {
+        unsafe {
+            let request = self.automation.CreateCacheRequest().context("Failed to create cache request")?;
+            for id in [UIA_NamePropertyId, UIA_LocalizedControlTypePropertyId, UIA_ControlTypePropertyId, UIA_AutomationIdPropertyId, UIA_BoundingRectanglePropertyId] {
+                request.AddProperty(id)?;
+            }
+            for property in Property::ALL {
+                if properties.contains(property) {
+                    request.AddProperty(property.property_id())?;
+                }
+            }
+            Ok(request)
+        }
+    }
+
+    fn traverse_cached(
+        &self,
+        element: &IUIAutomationElement,
+        request: &IUIAutomationCacheRequest,
+        depth: usize,
+        options: &TraversalOptions,
+        budget: &mut Budget,
+    ) -> Result<UiElement> {
+        budget.nodes += 1;
+        unsafe {
+            let mut ui_el = cached_element(element);
+            read_cached_properties(element, options.properties, &mut ui_el);
+
+            if depth < options.max_depth && budget.admit() {
+                let condition = self.automation.CreateTrueCondition().context("Failed to create condition")?;
+                budget.bound_transaction();
+                match element.FindAllBuildCache(TreeScope_Children, &condition, request) {
+                    Ok(children) => visit_children(&children, &mut ui_el, budget, |child, budget| {
+                        self.traverse_cached(child, request, depth + 1, options, budget)
+                    }),
+                    Err(_) if budget.expired() => budget.truncation_reason = Some("deadline"),
+                    Err(e) => return Err(e).context("Failed to find children"),
+                }
+            }
+
+            Ok(ui_el)
+        }
+    }
+}
+
+/// Name, type, id and bounds from the cache of `element`.
+fn cached_element(element: &IUIAutomationElement) -> UiElement {
+    unsafe {
+        let rect = element.CachedBoundingRectangle().unwrap_or_default();
+        UiElement {
+            name: element.CachedName().unwrap_or(BSTR::from("")).to_string(),
+            control_type: element.CachedLocalizedControlType().unwrap_or(BSTR::from("")).to_string(),
+            type_name: element.CachedControlType().map_or("", control_type_name),
+            automation_id: element.CachedAutomationId().unwrap_or(BSTR::from("")).to_string(),
+            rect: [rect.left, rect.top, rect.right, rect.bottom],
+            ..Default::default()
+        }
+    }
+}
+
+/// Visits children while the budget allows, recording the rest as `more_children`.
+fn visit_children<F>(children: &IUIAutomationElementArray, parent: &mut UiElement, budget: &mut Budget, mut visit: F)
+where
+    F: FnMut(&IUIAutomationElement, &mut Budget) -> Result<UiElement>,
+{
+    let count = unsafe { children.Length().unwrap_or(0) }.max(0) as usize;
+    let mut visited = 0;
+
+    for i in 0..count {
+        if visited >= budget.max_children {
+            if budget.truncation_reason.is_none() {
+                budget.truncation_reason = Some("max_children");
+            }
+            break;
+        }
+        if !budget.admit() {
+            break;
+        }
+        visited += 1;
+        if let Ok(child) = unsafe { children.GetElement(i as i32) }
+            && let Ok(child_tree) = visit(&child, budget)
+        {
+            parent.children.push(child_tree);
+        }
+    }
+
+    if visited < count {
+        parent.more_children = Some(count - visited);
+    }
+}
+
+/// Cached counterpart of `UiInspector::read_properties`; no cross-process calls.
+fn read_cached_properties(element: &IUIAutomationElement, properties: PropertySet, ui_el: &mut UiElement) {
+    unsafe {
+        if properties.contains(Property::IsEnabled) {
+            ui_el.is_enabled = element.CachedIsEnabled().ok().map(|b| b.as_bool());
+        }
+        if properties.contains(Property::IsOffscreen) {
+            ui_el.is_offscreen = element.CachedIsOffscreen().ok().map(|b| b.as_bool());
+        }
+        if properties.contains(Property::HasKeyboardFocus) {
+            ui_el.has_keyboard_focus = element.CachedHasKeyboardFocus().ok().map(|b| b.as_bool());
+        }
+        if properties.contains(Property::IsKeyboardFocusable) {
+            ui_el.is_keyboard_focusable = element.CachedIsKeyboardFocusable().ok().map(|b| b.as_bool());
+        }
+        if properties.contains(Property::Value) {
+            ui_el.value = element.GetCachedPropertyValue(UIA_ValueValuePropertyId).ok()
+                .and_then(|value| BSTR::try_from(&value).ok())
+                .and_then(non_empty);
+        }
+        if properties.contains(Property::ClassName) {
+            ui_el.class_name = element.CachedClassName().ok().and_then(non_empty);
+        }
+        if properties.contains(Property::FrameworkId) {
+            ui_el.framework_id = element.CachedFrameworkId().ok().and_then(non_empty);
+        }
+        if properties.contains(Property::ProcessId) {
+            ui_el.process_id = element.CachedProcessId().ok().map(|pid| pid as u32);
+        }
+        if properties.contains(Property::NativeWindowHandle) {
+            ui_el.native_window_handle = element.CachedNativeWindowHandle().ok()
+                .map(|hwnd| hwnd.0 as isize)
+                .filter(|&handle| handle != 0);
+        }
+        if properties.contains(Property::HelpText) {
+            ui_el.help_text = element.CachedHelpText().ok().and_then(non_empty);
+        }
+        if properties.contains(Property::AcceleratorKey) {
+            ui_el.accelerator_key = element.CachedAcceleratorKey().ok().and_then(non_empty);
+        }
+        if properties.contains(Property::AccessKey) {
+            ui_el.access_key = element.CachedAccessKey().ok().and_then(non_empty);
+        }
+    }
+}
+
+fn non_empty(value: BSTR) -> Option<String> {
+    let value = value.to_string();
+    if value.is_empty() { None } else { Some(value) }
+}
+
+const INTERACTIVE_CONTROL_TYPES: [UIA_CONTROLTYPE_ID; 15] = [
+    UIA_ButtonControlTypeId,
+    UIA_CheckBoxControlTypeId,
+    UIA_ComboBoxControlTypeId,
+    UIA_DataItemControlTypeId,
+    UIA_EditControlTypeId,
+    UIA_HeaderItemControlTypeId,
+    UIA_HyperlinkControlTypeId,
+    UIA_ListItemControlTypeId,
+    UIA_MenuItemControlTypeId,
+    UIA_RadioButtonControlTypeId,
+    UIA_SliderControlTypeId,
+    UIA_SpinnerControlTypeId,
+    UIA_SplitButtonControlTypeId,
+    UIA_TabItemControlTypeId,
+    UIA_TreeItemControlTypeId,
+];
+
+/// UIA control type ids with their language-independent names.
+const CONTROL_TYPES: [(UIA_CONTROLTYPE_ID, &str); 41] = [
+    (UIA_AppBarControlTypeId, "AppBar"),
+    (UIA_ButtonControlTypeId, "Button"),
+    (UIA_CalendarControlTypeId, "Calendar"),
+    (UIA_CheckBoxControlTypeId, "CheckBox"),
+    (UIA_ComboBoxControlTypeId, "ComboBox"),
+    (UIA_CustomControlTypeId, "Custom"),
+    (UIA_DataGridControlTypeId, "DataGrid"),
+    (UIA_DataItemControlTypeId, "DataItem"),
+    (UIA_DocumentControlTypeId, "Document"),
+    (UIA_EditControlTypeId, "Edit"),
+    (UIA_GroupControlTypeId, "Group"),
+    (UIA_HeaderControlTypeId, "Header"),
+    (UIA_HeaderItemControlTypeId, "HeaderItem"),
+    (UIA_HyperlinkControlTypeId, "Hyperlink"),
+    (UIA_ImageControlTypeId, "Image"),
+    (UIA_ListControlTypeId, "List"),
+    (UIA_ListItemControlTypeId, "ListItem"),
+    (UIA_MenuControlTypeId, "Menu"),
+    (UIA_MenuBarControlTypeId, "MenuBar"),
+    (UIA_MenuItemControlTypeId, "MenuItem"),
+    (UIA_PaneControlTypeId, "Pane"),
+    (UIA_ProgressBarControlTypeId, "ProgressBar"),
+    (UIA_RadioButtonControlTypeId, "RadioButton"),
+    (UIA_ScrollBarControlTypeId, "ScrollBar"),
+    (UIA_SemanticZoomControlTypeId, "SemanticZoom"),
+    (UIA_SeparatorControlTypeId, "Separator"),
+    (UIA_SliderControlTypeId, "Slider"),
+    (UIA_SpinnerControlTypeId, "Spinner"),
+    (UIA_SplitButtonControlTypeId, "SplitButton"),
+    (UIA_StatusBarControlTypeId, "StatusBar"),
+    (UIA_TabControlTypeId, "Tab"),
+    (UIA_TabItemControlTypeId, "TabItem"),
+    (UIA_TableControlTypeId, "Table"),
+    (UIA_TextControlTypeId, "Text"),
+    (UIA_ThumbControlTypeId, "Thumb"),
+    (UIA_TitleBarControlTypeId, "TitleBar"),
+    (UIA_ToolBarControlTypeId, "ToolBar"),
+    (UIA_ToolTipControlTypeId, "ToolTip"),
+    (UIA_TreeControlTypeId, "Tree"),
+    (UIA_TreeItemControlTypeId, "TreeItem"),
+    (UIA_WindowControlTypeId, "Window"),
+];
+
+/// Maps UIA control type names to their ids so selectors work regardless of UI language.
+fn control_type_id(name: &str) -> Option<UIA_CONTROLTYPE_ID> {
+    CONTROL_TYPES.iter().find(|(_, type_name)| type_name.eq_ignore_ascii_case(name)).map(|(id, _)| *id)
+}
+
+/// Language-independent name of a control type id, or "" for ids UIA added later.
+fn control_type_name(id: UIA_CONTROLTYPE_ID) -> &'static str {
+    CONTROL_TYPES.iter().find(|(type_id, _)| *type_id == id).map_or("", |(_, name)| *name)
 }
@@ -1,23 +1,64 @@
+#![recursion_limit = "256"]
+
 mod vision;
 mod executor;
 mod inspector;
 mod gui;
+mod wait;
+mod events;
+mod render;
+mod diff;
+mod window_manager;
+mod text;
+mod table;
+mod menu;
+mod dialogs;
+mod scroll;
+mod recorder;
+mod redaction;
 
 use mcp_rust_sdk::server::{Server, ServerHandler};
 use mcp_rust_sdk::transport::stdio::StdioTransport;
+use mcp_rust_sdk::transport::{Message, Transport};
+use mcp_rust_sdk::protocol::Notification;
 use mcp_rust_sdk::types::{Implementation, ClientCapabilities, ServerCapabilities};
 use mcp_rust_sdk::error::{Error, ErrorCode};
 use serde_json::json;
 use std::sync::Arc;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use vision::DesktopCapture;
+use vision::ocr::{OcrOutput, TextRecognizer};
+use vision::region::Region;
+use vision::marks::{self, Mark};
+use vision::template::{MatchOptions, Plane};
+use vision::fingerprint::Fingerprint;
+use vision::pixels::{self, PixelSample, RegionStats};
+use vision::grid::{self, GridSpec};
 use executor::HardwareExecutor;
-use inspector::UiInspector;
+use inspector::{UiInspector, UiElement, Selector, Property, PropertySet, TraversalMode, TraversalOptions};
+use wait::{WaitOutcome, WaitReport};
+use events::{EventMonitor, NOTIFICATION_QUEUE};
+use render::OutputFormat;
+use window_manager::{WindowAction, WindowBackend, Win32Backend};
+use text::{TextScope, TextTarget};
+use table::TableTarget;
+use scroll::ScrollDirection;
+use recorder::{Recording, RecordingOptions};
+use redaction::{Redaction, RedactionRules, Redactor};
+use windows::Win32::UI::Accessibility::IUIAutomationElement;
 use gui::JarvisGui;
 use async_trait::async_trait;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // Режим записи экрана из командной строки: `win_mcp record <path> ...`
+    let cli: Vec<String> = std::env::args().skip(1).collect();
+    if cli.first().map(String::as_str) == Some("record") {
+        return record_from_cli(&cli[1..]).await;
+    }
+
     // 1. Инициализация системных модулей
     let vision_res = DesktopCapture::new();
     let executor_res = HardwareExecutor::new();
@@ -30,17 +71,51 @@ async fn main() -> anyhow::Result<()> {
     let vision = Arc::new(Mutex::new(vision_res?));
     let executor = executor_res.ok().map(Arc::new);
     let inspector = Arc::new(inspector_res?);
+    let redaction_rules = RedactionRules::from_env()?;
+
+    // Подписка на события UI Automation; без неё сервер работает, но без событий
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::channel(NOTIFICATION_QUEUE);
+    let events = match EventMonitor::start(&inspector, event_tx) {
+        Ok(monitor) => Some(Arc::new(monitor)),
+        Err(e) => {
+            eprintln!("UI events unavailable: {:#}", e);
+            None
+        }
+    };
 
     // 2. Создаем транспорт и обработчик
     let (transport, _) = StdioTransport::new();
+    let transport = Arc::new(transport);
+    let windows: Arc<dyn WindowBackend> = Arc::new(Win32Backend);
     let handler = Arc::new(JarvisHandler {
         vision: vision.clone(),
         executor,
-        inspector,
+        inspector: inspector.clone(),
+        events: events.clone(),
+        snapshots: std::sync::Mutex::new(HashMap::new()),
+        windows: windows.clone(),
+        marks: std::sync::Mutex::new(Vec::new()),
+        recording: std::sync::Mutex::new(None),
+        redactor: Redactor::new(redaction_rules.clone(), inspector, windows),
+        startup_redaction: redaction_rules,
+    });
+
+    // Пересылка событий UI клиенту в виде MCP-уведомлений
+    let notifier = transport.clone();
+    tokio::spawn(async move {
+        while let Some(event) = event_rx.recv().await {
+            if !events.as_ref().is_some_and(|events| events.is_forwarding()) {
+                continue;
+            }
+            let notification = Notification::new("notifications/ui_event", Some(json!(event)));
+            if let Err(e) = notifier.send(Message::Notification(notification)).await {
+                eprintln!("Failed to forward UI event: {}", e);
+            }
+        }
     });
 
     // 3. Запуск сервера в фоне
-    let server = Server::new(Arc::new(transport), handler);
+    let server = Server::new(transport, handler);
     tokio::spawn(async move {
         if let Err(e) = server.start().await {
             eprintln!("MCP Server error: {}", e);
@@ -55,10 +130,43 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Records the screen until the duration or size limit is reached, or Ctrl+C.
+async fn record_from_cli(args: &[String]) -> anyhow::Result<()> {
+    let options = RecordingOptions::from_cli(args)?;
+    let vision = Arc::new(Mutex::new(DesktopCapture::new()?));
+    // Same rules as the server's images, so a recording never shows what a screenshot would hide.
+    let redactor = Redactor::new(RedactionRules::from_env()?, Arc::new(UiInspector::new()?), Arc::new(Win32Backend));
+    let recording = Recording::start(vision, redactor, options)?;
+    eprintln!("Recording to {}; press Ctrl+C to stop", recording.options().path.display());
+
+    while !recording.is_finished() {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(Duration::from_millis(200)) => {}
+        }
+    }
+    let summary = recording.stop().await?;
+    println!("{}", serde_json::to_string_pretty(&summary)?);
+    Ok(())
+}
+
 struct JarvisHandler {
     vision: Arc<Mutex<DesktopCapture>>,
     executor: Option<Arc<HardwareExecutor>>,
     inspector: Arc<UiInspector>,
+    /// `None` when subscribing to UI Automation events failed at startup.
+    events: Option<Arc<EventMonitor>>,
+    /// Last tree returned by `get_screen_metadata`, per scope and traversal options, for `mode: diff`.
+    snapshots: std::sync::Mutex<HashMap<String, UiElement>>,
+    windows: Arc<dyn WindowBackend>,
+    /// Marks drawn by the last annotated `capture_screen`, for `click_mark`.
+    marks: std::sync::Mutex<Vec<Mark>>,
+    /// Screen recording started by `start_recording`, if any.
+    recording: std::sync::Mutex<Option<Recording>>,
+    /// Blacks out areas of every image before it is encoded.
+    redactor: Redactor,
+    /// Rules loaded at startup; `configure_redaction` can widen them but not go below these.
+    startup_redaction: RedactionRules,
 }
 
 #[async_trait]
@@ -68,12 +176,18 @@ impl ServerHandler for JarvisHandler {
         _implementation: Implementation,
         _capabilities: ClientCapabilities,
     ) -> Result<ServerCapabilities, Error> {
+        if let Some(events) = &self.events {
+            events.set_forwarding(true);
+        }
         Ok(ServerCapabilities {
             custom: Some(vec![("tools".to_string(), json!({}))].into_iter().collect()),
         })
     }
 
     async fn shutdown(&self) -> Result<(), Error> {
+        if let Some(events) = &self.events {
+            events.set_forwarding(false);
+        }
         Ok(())
     }
 
@@ -92,18 +206,364 @@ impl ServerHandler for JarvisHandler {
                             "inputSchema": {
                                 "type": "object",
                                 "properties": {
-                                    "max_depth": { "type": "integer", "default": 3 }
+                                    "window": { "type": "string", "description": "Часть заголовка окна; по умолчанию весь рабочий стол" },
+                                    "mode": {
+                                        "type": "string",
+                                        "enum": ["full", "diff"],
+                                        "default": "full",
+                                        "description": "diff: добавленные, удаленные и измененные элементы с прошлого вызова для того же окна"
+                                    },
+                                    "max_depth": { "type": "integer", "default": 3 },
+                                    "max_nodes": { "type": "integer", "default": 2000 },
+                                    "max_children": { "type": "integer", "default": 200, "description": "Остальные дети помечаются как \"+N more\"" },
+                                    "timeout_ms": { "type": "integer", "default": 10000, "description": "После дедлайна возвращается частичное дерево с флагом truncated" },
+                                    "properties": properties_schema(),
+                                    "traversal": {
+                                        "type": "string",
+                                        "enum": ["cached", "live"],
+                                        "default": "cached",
+                                        "description": "cached: пакетная загрузка через CacheRequest; live: старый обход по одному свойству"
+                                    },
+                                    "format": {
+                                        "type": "string",
+                                        "enum": ["json", "outline", "markdown", "yaml", "xml"],
+                                        "default": "json",
+                                        "description": "outline: [button] \"Save\" #id @(x,y,w,h); xml: Appium-style page source"
+                                    }
+                                }
+                            }
+                        },
+                        {
+                            "name": "wait_for_element",
+                            "description": "Дождаться, пока элемент появится, исчезнет или станет доступным",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "selector": selector_schema(),
+                                    "state": { "type": "string", "enum": ["appear", "disappear", "enabled"], "default": "appear" },
+                                    "timeout_ms": { "type": "integer", "default": 10000 },
+                                    "interval_ms": { "type": "integer", "default": 250 }
+                                },
+                                "required": ["selector"]
+                            }
+                        },
+                        {
+                            "name": "wait_for_window",
+                            "description": "Дождаться появления или закрытия окна по части заголовка",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "title": { "type": "string" },
+                                    "state": { "type": "string", "enum": ["appear", "disappear"], "default": "appear" },
+                                    "timeout_ms": { "type": "integer", "default": 10000 },
+                                    "interval_ms": { "type": "integer", "default": 250 }
+                                },
+                                "required": ["title"]
+                            }
+                        },
+                        {
+                            "name": "wait_for_screen_stable",
+                            "description": "Дождаться, пока экран (или область) перестанет меняться на stable_ms; возвращает итоговый кадр. Использовать после клика вместо фиксированной паузы",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "region": region_schema(),
+                                    "stable_ms": { "type": "integer", "default": 500 },
+                                    "tolerance": { "type": "number", "default": 0.0, "description": "Доля ячеек сетки, изменение которых не считается изменением (0-1)" },
+                                    "include_image": { "type": "boolean", "default": true },
+                                    "timeout_ms": { "type": "integer", "default": 10000 },
+                                    "interval_ms": { "type": "integer", "default": 250 }
+                                }
+                            }
+                        },
+                        {
+                            "name": "wait_for_change",
+                            "description": "Дождаться, пока экран (или область) изменится относительно текущего состояния; возвращает новый кадр и прямоугольник изменений",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "region": region_schema(),
+                                    "tolerance": { "type": "number", "default": 0.0, "description": "Доля ячеек сетки, изменение которых не считается изменением (0-1)" },
+                                    "include_image": { "type": "boolean", "default": true },
+                                    "timeout_ms": { "type": "integer", "default": 10000 },
+                                    "interval_ms": { "type": "integer", "default": 250 }
+                                }
+                            }
+                        },
+                        {
+                            "name": "get_recent_events",
+                            "description": "Последние события UI: фокус, открытие/закрытие окон, изменения структуры и свойств",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "limit": { "type": "integer", "default": 50 },
+                                    "kinds": {
+                                        "type": "array",
+                                        "items": { "type": "string", "enum": ["focus_changed", "structure_changed", "window_opened", "window_closed", "property_changed"] }
+                                    },
+                                    "since_ms": { "type": "integer", "description": "Только события новее этой метки времени (Unix, мс)" }
+                                }
+                            }
+                        },
+                        {
+                            "name": "get_text",
+                            "description": "Прочитать текст документа через TextPattern (весь документ, видимая часть или выделение) с постраничной выдачей, позицией каретки и выделением",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "selector": selector_schema(),
+                                    "scope": { "type": "string", "enum": ["document", "visible", "selection"], "default": "document" },
+                                    "offset": { "type": "integer", "default": 0 },
+                                    "limit": { "type": "integer", "default": 4000 }
                                 }
                             }
                         },
+                        {
+                            "name": "select_text",
+                            "description": "Выделить текст по строке поиска или по смещениям символов; текущее выделение читается через get_text со scope=selection",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "selector": selector_schema(),
+                                    "find": { "type": "string" },
+                                    "occurrence": { "type": "integer", "default": 1 },
+                                    "ignore_case": { "type": "boolean", "default": false },
+                                    "start": { "type": "integer" },
+                                    "end": { "type": "integer" }
+                                }
+                            }
+                        },
+                        {
+                            "name": "set_caret",
+                            "description": "Переместить каретку на абсолютное смещение или на delta символов от текущей позиции",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "selector": selector_schema(),
+                                    "offset": { "type": "integer" },
+                                    "delta": { "type": "integer" }
+                                }
+                            }
+                        },
+                        {
+                            "name": "read_table",
+                            "description": "Прочитать таблицу или сетку (GridPattern/TablePattern): заголовки и строки в JSON или CSV с постраничной выдачей",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "selector": selector_schema(),
+                                    "format": { "type": "string", "enum": ["json", "csv"], "default": "json" },
+                                    "row_offset": { "type": "integer", "default": 0 },
+                                    "row_limit": { "type": "integer", "default": 50 }
+                                }
+                            }
+                        },
+                        {
+                            "name": "table_cell",
+                            "description": "Действие над ячейкой таблицы: прочитать, нажать, выделить или изменить значение",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "selector": selector_schema(),
+                                    "row": { "type": "integer" },
+                                    "column": { "type": "integer" },
+                                    "action": { "type": "string", "enum": ["get", "click", "set_value"], "default": "get" },
+                                    "value": { "type": "string" }
+                                },
+                                "required": ["row", "column"]
+                            }
+                        },
+                        {
+                            "name": "invoke_menu",
+                            "description": "Выбрать пункт меню по пути (например [\"File\", \"Save As...\"]) в строке меню окна или в открытом контекстном меню",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "path": { "type": "array", "items": { "type": "string" } },
+                                    "window": { "type": "string", "description": "Подстрока заголовка окна; по умолчанию активное окно" }
+                                },
+                                "required": ["path"]
+                            }
+                        },
+                        {
+                            "name": "dismiss_dialog",
+                            "description": "Закрыть диалоговое окно (сохранение, ошибка и т.п.) нажатием кнопки с указанным названием",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "button": { "type": "string", "description": "Название кнопки, например \"Don't Save\"" },
+                                    "handle": { "type": "integer", "description": "Дескриптор диалога; по умолчанию верхний модальный диалог" },
+                                    "window": { "type": "string", "description": "Подстрока заголовка окна приложения, чьи диалоги искать; по умолчанию активное приложение" },
+                                    "title": { "type": "string", "description": "Подстрока заголовка диалога" }
+                                },
+                                "required": ["button"]
+                            }
+                        },
+                        {
+                            "name": "get_focused_element",
+                            "description": "Получить элемент с клавиатурным фокусом со всеми свойствами и цепочкой предков до рабочего стола",
+                            "inputSchema": { "type": "object", "properties": {} }
+                        },
+                        {
+                            "name": "set_focus",
+                            "description": "Перевести клавиатурный фокус на элемент",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": { "selector": selector_schema() },
+                                "required": ["selector"]
+                            }
+                        },
+                        {
+                            "name": "type_text",
+                            "description": "Ввести текст с клавиатуры (только строчные латинские буквы a-z, иначе ошибка); при указании expect_focus текст вводится только если фокус на подходящем элементе",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "text": { "type": "string" },
+                                    "expect_focus": selector_schema()
+                                },
+                                "required": ["text"]
+                            }
+                        },
+                        {
+                            "name": "scroll_into_view",
+                            "description": "Прокрутить контейнер так, чтобы элемент стал видимым (ScrollItemPattern), с реализацией виртуализированных элементов",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": { "selector": selector_schema() },
+                                "required": ["selector"]
+                            }
+                        },
+                        {
+                            "name": "find_by_scrolling",
+                            "description": "Искать элемент в прокручиваемом контейнере, прокручивая его постранично до совпадения или до конца",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "container": selector_schema(),
+                                    "selector": selector_schema(),
+                                    "direction": { "type": "string", "enum": ["down", "right"], "default": "down" },
+                                    "max_steps": { "type": "integer", "default": 50 }
+                                },
+                                "required": ["container", "selector"]
+                            }
+                        },
                         {
                             "name": "capture_screen",
-                            "description": "Сделать скриншот рабочего стола (возвращает base64)",
+                            "description": "Сделать скриншот рабочего стола (возвращает base64); marks добавляет пронумерованные рамки интерактивных элементов или слов OCR и легенду к ним",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "include_cursor": { "type": "boolean", "default": true, "description": "Нарисовать указатель мыши на скриншоте" },
+                                    "grid": { "type": "integer", "description": "Шаг координатной сетки в пикселях экрана (например 100); без параметра сетки нет" },
+                                    "marks": { "type": "string", "enum": ["none", "ui", "ocr"], "default": "none" },
+                                    "window": { "type": "string", "description": "Подстрока заголовка окна для marks=ui; по умолчанию активное окно" },
+                                    "max_marks": { "type": "integer", "default": 150 }
+                                }
+                            }
+                        },
+                        {
+                            "name": "configure_redaction",
+                            "description": "Добавить правила затирания на скриншотах: окна по части заголовка, процессы и прямоугольники экрана. Поля паролей затираются всегда; reset возвращает правила запуска",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "window_titles": { "type": "array", "items": { "type": "string" } },
+                                    "processes": { "type": "array", "items": { "type": "string" }, "description": "Имена процессов, например KeePass.exe" },
+                                    "rects": { "type": "array", "items": region_schema() },
+                                    "reset": { "type": "boolean", "default": false }
+                                }
+                            }
+                        },
+                        {
+                            "name": "zoom",
+                            "description": "Увеличить область экрана с сеткой, подписанной реальными координатами экрана, чтобы уточнить точку клика после общего скриншота",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "region": region_schema(),
+                                    "scale": { "type": "integer", "description": "Целое увеличение (1-16); по умолчанию до ~1024 пикселей по длинной стороне" },
+                                    "grid_step": { "type": "integer", "description": "Шаг сетки в пикселях экрана; по умолчанию подбирается по увеличению" }
+                                },
+                                "required": ["region"]
+                            }
+                        },
+                        {
+                            "name": "click_mark",
+                            "description": "Кликнуть по центру рамки с номером из последнего скриншота с marks",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": { "mark": { "type": "integer" } },
+                                "required": ["mark"]
+                            }
+                        },
+                        {
+                            "name": "ocr_screen",
+                            "description": "Распознать текст на экране или в его области (офлайн OCR): строки и слова с координатами",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "region": region_schema(),
+                                    "language": { "type": "string", "description": "Тег языка BCP-47, например en-US; по умолчанию языки профиля" },
+                                    "scale": { "type": "integer", "default": 1, "description": "Увеличение перед распознаванием (1-4) для мелкого шрифта" }
+                                }
+                            }
+                        },
+                        {
+                            "name": "find_image",
+                            "description": "Найти на экране изображение-шаблон (PNG в base64): нормированная кросс-корреляция в нескольких масштабах, возвращает прямоугольники и оценки",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "template": { "type": "string", "description": "PNG в base64 (можно data:image/png;base64,...)" },
+                                    "region": region_schema(),
+                                    "threshold": { "type": "number", "default": 0.8 },
+                                    "max_results": { "type": "integer", "default": 5 },
+                                    "scales": { "type": "array", "items": { "type": "number" }, "default": [1.0] }
+                                },
+                                "required": ["template"]
+                            }
+                        },
+                        {
+                            "name": "get_pixels",
+                            "description": "Цвета пикселей в точках и средний/преобладающие цвета прямоугольников по кэшированному кадру, без отправки скриншота (проверить цвет индикатора, заполненность прогресс-бара). Затертые области читаются как черные",
                             "inputSchema": {
                                 "type": "object",
-                                "properties": {}
+                                "properties": {
+                                    "points": {
+                                        "type": "array",
+                                        "maxItems": MAX_PIXEL_POINTS,
+                                        "items": {
+                                            "type": "object",
+                                            "properties": { "x": { "type": "integer" }, "y": { "type": "integer" } },
+                                            "required": ["x", "y"]
+                                        }
+                                    },
+                                    "regions": { "type": "array", "maxItems": MAX_PIXEL_REGIONS, "items": region_schema() }
+                                }
                             }
                         },
+                        {
+                            "name": "start_recording",
+                            "description": "Начать запись экрана (с курсором и с теми же правилами скрытия, что и у снимков) в анимированный GIF или в папку PNG-кадров с manifest frames.json; запись останавливается по stop_recording или по лимитам длительности и размера",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "path": { "type": "string", "description": "Файл .gif или папка для кадров" },
+                                    "format": { "type": "string", "enum": ["gif", "frames"], "description": "По умолчанию gif для путей .gif, иначе frames" },
+                                    "fps": { "type": "integer", "default": 5, "description": "Кадров в секунду (1-30)" },
+                                    "max_duration_s": { "type": "number", "default": 60 },
+                                    "max_size_mb": { "type": "integer", "default": 200 },
+                                    "scale": { "type": "number", "default": 1.0, "description": "Уменьшение кадров, (0, 1]" }
+                                },
+                                "required": ["path"]
+                            }
+                        },
+                        {
+                            "name": "stop_recording",
+                            "description": "Остановить запись экрана и вернуть сводку: число кадров, пропущенные кадры, длительность, размер и причину остановки",
+                            "inputSchema": { "type": "object", "properties": {} }
+                        },
                         {
                             "name": "execute_click",
                             "description": "Выполнить клик мышью по координатам",
@@ -116,6 +576,33 @@ impl ServerHandler for JarvisHandler {
                                 "required": ["x", "y"]
                             }
                         },
+                        {
+                            "name": "list_windows",
+                            "description": "Список окон: заголовок, процесс, PID, границы, монитор, z-порядок и состояние",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "title": { "type": "string", "description": "Фильтр по части заголовка" },
+                                    "process_name": { "type": "string", "description": "Фильтр по имени процесса (например, notepad.exe)" }
+                                }
+                            }
+                        },
+                        {
+                            "name": "window_action",
+                            "description": "Действие над окном по handle из list_windows",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "handle": { "type": "integer" },
+                                    "action": { "type": "string", "enum": ["focus", "minimize", "maximize", "restore", "close", "move", "resize"] },
+                                    "x": { "type": "integer" },
+                                    "y": { "type": "integer" },
+                                    "width": { "type": "integer" },
+                                    "height": { "type": "integer" }
+                                },
+                                "required": ["handle", "action"]
+                            }
+                        },
                         {
                             "name": "open_url",
                             "description": "Открыть URL в браузере по умолчанию",
@@ -146,33 +633,699 @@ impl ServerHandler for JarvisHandler {
                 let tool_name = params["name"].as_str().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing tool name"))?;
                 let args = params["arguments"].clone();
 
-                match tool_name {
+                let result = match tool_name {
                     "get_screen_metadata" => {
                         let max_depth = args["max_depth"].as_u64().unwrap_or(3) as usize;
-                        let tree = self.inspector.get_ui_tree(max_depth).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let format = parse_format(&args["format"])?;
+                        let properties = PropertySet::parse(&args["properties"]).map_err(|e| Error::protocol(ErrorCode::InvalidParams, e.to_string()))?;
+                        let mode = match args["traversal"].as_str() {
+                            None => TraversalMode::Cached,
+                            Some(name) => TraversalMode::parse(name)
+                                .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, format!("Unknown traversal: {}", name)))?,
+                        };
+                        let diff_mode = match args["mode"].as_str().unwrap_or("full") {
+                            "full" => false,
+                            "diff" => true,
+                            other => return Err(Error::protocol(ErrorCode::InvalidParams, format!("Unknown mode: {}", other))),
+                        };
+                        let window = args["window"].as_str().map(String::from);
+                        let scope = window.clone().unwrap_or_else(|| "desktop".to_string());
+                        let options = TraversalOptions {
+                            window,
+                            max_depth,
+                            properties,
+                            mode,
+                            max_nodes: args["max_nodes"].as_u64().unwrap_or(2000) as usize,
+                            max_children: args["max_children"].as_u64().unwrap_or(200) as usize,
+                            timeout: Duration::from_millis(args["timeout_ms"].as_u64().unwrap_or(10_000)),
+                        };
+                        let (tree, stats) = self.inspector.get_ui_tree(&options).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        // Trees walked with different limits or properties differ for that reason alone.
+                        let key = snapshot_key(&scope, &options);
+                        let previous = self.snapshots.lock().unwrap().insert(key, tree.clone());
+                        let (text, summary) = match previous {
+                            Some(previous) if diff_mode => {
+                                let changes = diff::diff_trees(&previous, &tree);
+                                let text = serde_json::to_string(&changes).unwrap_or_default();
+                                let mut summary = json!({
+                                    "scope": scope,
+                                    "unchanged": changes.is_empty(),
+                                    "estimated_tokens": render::estimate_tokens(&text),
+                                    "traversal": stats
+                                });
+                                if stats.truncated {
+                                    summary["warning"] = json!("traversal was truncated; elements past the limit may show up as added or removed");
+                                }
+                                (text, summary)
+                            }
+                            _ => {
+                                let text = render::render(&tree, format);
+                                let mut summary = json!({ "format": format.name(), "estimated_tokens": render::estimate_tokens(&text), "traversal": stats });
+                                if diff_mode {
+                                    summary["diff"] = json!("no previous snapshot for this scope and these traversal options; returned the full tree");
+                                }
+                                (text, summary)
+                            }
+                        };
+                        Ok(json!({
+                            "content": [
+                                {"type": "text", "text": text},
+                                {"type": "text", "text": summary.to_string()}
+                            ]
+                        }))
+                    }
+                    "wait_for_element" => {
+                        let selector = parse_selector(&args["selector"])?;
+                        let state = args["state"].as_str().unwrap_or("appear");
+                        if !matches!(state, "appear" | "disappear" | "enabled") {
+                            return Err(Error::protocol(ErrorCode::InvalidParams, format!("Unknown state: {}", state)));
+                        }
+                        let (timeout, interval) = wait::timing_from_args(&args);
+
+                        // Each check searches the whole desktop, so it runs off the async workers and
+                        // its UIA calls are bounded by the time left.
+                        let inspector = self.inspector.clone();
+                        let target = state.to_string();
+                        let report = wait::poll_blocking(timeout, interval, move |remaining| inspector.within(remaining, || {
+                            let found = inspector.find_first(&selector)?;
+                            Ok(match (target.as_str(), found) {
+                                ("disappear", None) => Some(None),
+                                ("appear", Some(element)) => Some(Some(inspector.describe(&element)?)),
+                                ("enabled", Some(element)) if inspector.is_enabled(&element) => Some(Some(inspector.describe(&element)?)),
+                                _ => None,
+                            })
+                        })).await.map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        Ok(wait_response(state, report))
+                    }
+                    "wait_for_window" => {
+                        let title = args["title"].as_str().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing title"))?;
+                        let state = args["state"].as_str().unwrap_or("appear");
+                        if !matches!(state, "appear" | "disappear") {
+                            return Err(Error::protocol(ErrorCode::InvalidParams, format!("Unknown state: {}", state)));
+                        }
+                        let (timeout, interval) = wait::timing_from_args(&args);
+
+                        let inspector = self.inspector.clone();
+                        let (title, target) = (title.to_string(), state.to_string());
+                        let report = wait::poll_blocking(timeout, interval, move |remaining| inspector.within(remaining, || {
+                            let found = inspector.find_window(&title)?;
+                            Ok(match (target.as_str(), found) {
+                                ("disappear", None) => Some(None),
+                                ("appear", Some(window)) => Some(Some(inspector.describe(&window)?)),
+                                _ => None,
+                            })
+                        })).await.map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        Ok(wait_response(state, report))
+                    }
+                    "wait_for_screen_stable" => {
+                        let (width, height) = self.vision.lock().await.get_dimensions();
+                        let region = screen_region(&args["region"], width, height)?;
+                        let stable_for = Duration::from_millis(args["stable_ms"].as_u64().unwrap_or(500));
+                        let tolerance = args["tolerance"].as_f64().unwrap_or(0.0) as f32;
+                        let (timeout, interval) = wait::timing_from_args(&args);
+
+                        // The reference only moves when a change is detected, so slow drift still counts.
+                        let mut reference: Option<Fingerprint> = None;
+                        let mut last_change = Instant::now();
+                        let vision = &self.vision;
+                        let report = wait::poll_until(timeout, interval, || {
+                            // A running recording holds the capture briefly; skip the attempt instead of blocking.
+                            let Ok(mut vision) = vision.try_lock() else { return Ok(None) };
+                            let changes = vision.refresh()?;
+                            if reference.is_none() || changes.touches(region) {
+                                let current = Fingerprint::of(vision.frame(), width, region);
+                                let changed = reference.as_ref().is_none_or(|r| r.compare(&current).changed_fraction > tolerance);
+                                if changed {
+                                    if reference.is_some() {
+                                        last_change = Instant::now();
+                                    }
+                                    reference = Some(current);
+                                }
+                            }
+                            Ok((last_change.elapsed() >= stable_for).then(|| json!({ "stable_ms": last_change.elapsed().as_millis() as u64 })))
+                        }).await.map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        self.screen_wait_response("stable", report, &args, region).await
+                    }
+                    "wait_for_change" => {
+                        let mut vision = self.vision.lock().await;
+                        let (width, height) = vision.get_dimensions();
+                        let region = screen_region(&args["region"], width, height)?;
+                        vision.refresh().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let baseline = Fingerprint::of(vision.frame(), width, region);
+                        drop(vision);
+                        let tolerance = args["tolerance"].as_f64().unwrap_or(0.0) as f32;
+                        let (timeout, interval) = wait::timing_from_args(&args);
+
+                        let vision = &self.vision;
+                        let report = wait::poll_until(timeout, interval, || {
+                            let Ok(mut vision) = vision.try_lock() else { return Ok(None) };
+                            // Frame metadata rules out most polls without hashing the region.
+                            if !vision.refresh()?.touches(region) {
+                                return Ok(None);
+                            }
+                            let difference = baseline.compare(&Fingerprint::of(vision.frame(), width, region));
+                            Ok((difference.changed_fraction > tolerance).then(|| json!(difference)))
+                        }).await.map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        self.screen_wait_response("changed", report, &args, region).await
+                    }
+                    "get_recent_events" => {
+                        let limit = args["limit"].as_u64().unwrap_or(50) as usize;
+                        let since_ms = args["since_ms"].as_u64().unwrap_or(0);
+                        let kinds: Vec<String> = args["kinds"].as_array()
+                            .map(|kinds| kinds.iter().filter_map(|k| k.as_str().map(String::from)).collect())
+                            .unwrap_or_default();
+
+                        let monitor = self.events.as_ref()
+                            .ok_or_else(|| Error::protocol(ErrorCode::RequestFailed, "UI event monitoring failed to start"))?;
+                        let events = monitor.buffer().recent(limit, &kinds, since_ms);
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&events).unwrap_or_default()}]
+                        }))
+                    }
+                    "get_text" => {
+                        let scope = match args["scope"].as_str() {
+                            None => TextScope::Document,
+                            Some(name) => TextScope::parse(name)
+                                .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, format!("Unknown scope: {}", name)))?,
+                        };
+                        let offset = args["offset"].as_u64().unwrap_or(0) as usize;
+                        let limit = args["limit"].as_u64().unwrap_or(4000) as usize;
+
+                        let element = self.resolve_target(&args["selector"])?;
+                        let target = TextTarget::locate(&self.inspector, &element).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let report = target.read(scope, offset, limit).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&report).unwrap_or_default()}]
+                        }))
+                    }
+                    "select_text" => {
+                        let element = self.resolve_target(&args["selector"])?;
+                        let target = TextTarget::locate(&self.inspector, &element).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        let range = if let Some(needle) = args["find"].as_str() {
+                            let occurrence = args["occurrence"].as_u64().unwrap_or(1) as usize;
+                            let ignore_case = args["ignore_case"].as_bool().unwrap_or(false);
+                            target.find(needle, occurrence, ignore_case)
+                                .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?
+                                .ok_or_else(|| Error::protocol(ErrorCode::RequestFailed, format!("Text not found: {}", needle)))?
+                        } else {
+                            let start = args["start"].as_u64().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Either find or start/end is required"))?;
+                            let end = args["end"].as_u64().unwrap_or(start);
+                            target.range_at(start as usize, end as usize).map_err(|e| Error::protocol(ErrorCode::InvalidParams, e.to_string()))?
+                        };
+
+                        target.select(&range).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let report = target.read(TextScope::Selection, 0, 4000).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&report).unwrap_or_default()}]
+                        }))
+                    }
+                    "set_caret" => {
+                        let element = self.resolve_target(&args["selector"])?;
+                        let target = TextTarget::locate(&self.inspector, &element).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        let offset = match (args["offset"].as_u64(), args["delta"].as_i64()) {
+                            (Some(offset), _) => offset as usize,
+                            (None, Some(delta)) => {
+                                let current = target.caret_offset()
+                                    .ok_or_else(|| Error::protocol(ErrorCode::RequestFailed, "The control does not report its caret (TextPattern2)"))?;
+                                current.saturating_add_signed(delta as isize)
+                            }
+                            (None, None) => return Err(Error::protocol(ErrorCode::InvalidParams, "Either offset or delta is required")),
+                        };
+
+                        let range = target.range_at(offset, offset).map_err(|e| Error::protocol(ErrorCode::InvalidParams, e.to_string()))?;
+                        target.select(&range).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let report = target.read(TextScope::Selection, 0, 0).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&report).unwrap_or_default()}]
+                        }))
+                    }
+                    "read_table" => {
+                        let row_offset = args["row_offset"].as_u64().unwrap_or(0) as usize;
+                        let row_limit = args["row_limit"].as_u64().unwrap_or(50) as usize;
+
+                        let element = self.resolve_target(&args["selector"])?;
+                        let table = TableTarget::locate(&self.inspector, &element).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let data = table.read(row_offset, row_limit).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        let text = match args["format"].as_str().unwrap_or("json") {
+                            "json" => serde_json::to_string(&data).unwrap_or_default(),
+                            "csv" => table::to_csv(&data),
+                            other => return Err(Error::protocol(ErrorCode::InvalidParams, format!("Unknown format: {}", other))),
+                        };
+                        let summary = json!({
+                            "row_offset": data.row_offset,
+                            "rows": data.rows.len(),
+                            "total_rows": data.total_rows,
+                            "total_columns": data.total_columns,
+                            "has_more": data.has_more
+                        });
+                        Ok(json!({
+                            "content": [
+                                {"type": "text", "text": text},
+                                {"type": "text", "text": summary.to_string()}
+                            ]
+                        }))
+                    }
+                    "table_cell" => {
+                        let row = args["row"].as_u64().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing row"))? as usize;
+                        let column = args["column"].as_u64().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing column"))? as usize;
+
+                        let element = self.resolve_target(&args["selector"])?;
+                        let table = TableTarget::locate(&self.inspector, &element).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let cell = table.cell(row, column).map_err(|e| Error::protocol(ErrorCode::InvalidParams, e.to_string()))?;
+
+                        match args["action"].as_str().unwrap_or("get") {
+                            "get" => {}
+                            "click" => self.inspector.invoke(&cell).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?,
+                            "set_value" => {
+                                let value = args["value"].as_str().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing value"))?;
+                                self.inspector.set_value(&cell, value).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                            }
+                            other => return Err(Error::protocol(ErrorCode::InvalidParams, format!("Unknown action: {}", other))),
+                        }
+
+                        let described = self.inspector.describe(&cell).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&described).unwrap_or_default()}]
+                        }))
+                    }
+                    "invoke_menu" => {
+                        let path: Vec<String> = args["path"].as_array()
+                            .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing path"))?
+                            .iter()
+                            .map(|segment| segment.as_str().map(String::from).ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Path segments must be strings")))
+                            .collect::<Result<_, _>>()?;
+                        if path.is_empty() {
+                            return Err(Error::protocol(ErrorCode::InvalidParams, "Path is empty"));
+                        }
+
+                        // Submenu waits block, so the walk runs off the async workers. UIA elements are
+                        // not `Send`, which is why the window is looked up on that thread too.
+                        let inspector = self.inspector.clone();
+                        let executor = self.executor.clone();
+                        let window = args["window"].as_str().map(String::from);
+                        let outcome = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+                            let root = match &window {
+                                Some(title) => inspector.find_window(title)?.ok_or_else(|| anyhow::anyhow!("Window not found: {}", title))?,
+                                None => inspector.foreground_window()?,
+                            };
+                            Ok(menu::invoke_path(&inspector, executor.as_deref(), &root, &path))
+                        }).await
+                            .map_err(|e| Error::protocol(ErrorCode::InternalError, e.to_string()))?
+                            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        match outcome {
+                            Ok(steps) => Ok(json!({
+                                "content": [{"type": "text", "text": json!({ "status": "invoked", "steps": steps }).to_string()}]
+                            })),
+                            Err(failure) => Ok(json!({
+                                "content": [{"type": "text", "text": json!({ "status": "failed", "failure": failure }).to_string()}],
+                                "isError": true
+                            })),
+                        }
+                    }
+                    "dismiss_dialog" => {
+                        let button = args["button"].as_str().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing button"))?;
+                        let title = args["title"].as_str().map(str::to_lowercase);
+
+                        // An explicit handle names the dialog exactly, whichever app owns it.
+                        let process_id = match args["handle"].as_u64() {
+                            Some(_) => None,
+                            None => Some(self.target_process(&args)?),
+                        };
+                        let dialogs = dialogs::detect(self.windows.as_ref(), &self.inspector, process_id, false)
+                            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let dialog = dialogs.iter()
+                            .filter(|d| args["handle"].as_u64().is_none_or(|handle| d.window.window.handle == handle))
+                            .filter(|d| title.as_ref().is_none_or(|t| d.window.window.title.to_lowercase().contains(t)))
+                            // Without an explicit target, prefer the dialog that is blocking input.
+                            .min_by_key(|d| !d.window.modal)
+                            .ok_or_else(|| Error::protocol(ErrorCode::RequestFailed, "No matching dialog is open"))?;
+
+                        dialogs::dismiss(&self.inspector, dialog.window.window.handle, button)
+                            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": format!("Pressed \"{}\" in dialog \"{}\"", button, dialog.window.window.title)}]
+                        }))
+                    }
+                    "get_focused_element" => {
+                        let focused = self.inspector.focused().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let element = self.inspector.describe(&focused).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let ancestors: Vec<UiElement> = self.inspector.ancestors(&focused)
+                            .iter()
+                            .filter_map(|ancestor| self.inspector.describe(ancestor).ok())
+                            .collect();
                         Ok(json!({
-                            "content": [{"type": "text", "text": serde_json::to_string(&tree).unwrap_or_default()}]
+                            "content": [{"type": "text", "text": json!({ "element": element, "ancestors": ancestors }).to_string()}]
                         }))
                     }
+                    "set_focus" => {
+                        let element = self.resolve_target(&args["selector"])?;
+                        self.inspector.set_focus(&element).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        let focused = self.inspector.focused().and_then(|f| self.inspector.describe(&f))
+                            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&focused).unwrap_or_default()}]
+                        }))
+                    }
+                    "type_text" => {
+                        let executor = self.executor.as_ref().ok_or_else(|| {
+                            Error::protocol(ErrorCode::MethodNotFound, "Hardware executor is not available (driver missing)")
+                        })?;
+                        let text = args["text"].as_str().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing text"))?;
+                        // Rejected up front, so a partially typed string never reaches the target.
+                        let mut unsupported: Vec<char> = text.chars().filter(|c| !executor::can_type(*c)).collect();
+                        unsupported.sort_unstable();
+                        unsupported.dedup();
+                        if !unsupported.is_empty() {
+                            let listed: String = unsupported.iter().map(|c| format!("{:?}", c)).collect::<Vec<_>>().join(", ");
+                            return Err(Error::protocol(ErrorCode::InvalidParams, format!(
+                                "type_text can only send lowercase a-z; unsupported characters: {}", listed
+                            )));
+                        }
+
+                        if !args["expect_focus"].is_null() {
+                            let expected = parse_selector(&args["expect_focus"])?;
+                            let focused = self.inspector.focused().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                            let matched = self.inspector.matches(&focused, &expected).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                            if !matched {
+                                let actual = self.inspector.describe(&focused).ok();
+                                let body = json!({ "status": "focus_mismatch", "focused": actual });
+                                return Ok(json!({
+                                    "content": [{"type": "text", "text": body.to_string()}],
+                                    "isError": true
+                                }));
+                            }
+                        }
+
+                        executor.type_text(text).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": format!("Typed {} characters", text.chars().count())}]
+                        }))
+                    }
+                    "scroll_into_view" => {
+                        let element = self.resolve_target(&args["selector"])?;
+                        scroll::scroll_into_view(&element).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        let described = self.inspector.describe(&element).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&described).unwrap_or_default()}]
+                        }))
+                    }
+                    "find_by_scrolling" => {
+                        let container = match &args["container"] {
+                            serde_json::Value::Null => None,
+                            value => Some(parse_selector(value)?),
+                        };
+                        let selector = parse_selector(&args["selector"])?;
+                        let direction = match args["direction"].as_str() {
+                            None => ScrollDirection::Down,
+                            Some(name) => ScrollDirection::parse(name)
+                                .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, format!("Unknown direction: {}", name)))?,
+                        };
+                        let max_steps = args["max_steps"].as_u64().unwrap_or(50) as usize;
+
+                        // Each step sleeps while the list settles, so the search runs off the async workers.
+                        // UIA elements are not `Send`: the container is resolved and the match described there.
+                        let inspector = self.inspector.clone();
+                        let (steps, reached_end, method, found) = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+                            let container = match &container {
+                                Some(selector) => inspector.find_first(selector)?.ok_or_else(|| anyhow::anyhow!("No element matches the selector"))?,
+                                None => inspector.focused()?,
+                            };
+                            let search = scroll::find_by_scrolling(&inspector, &container, &selector, direction, max_steps)?;
+                            let found = search.found.map(|element| inspector.describe(&element)).transpose()?;
+                            Ok((search.steps, search.reached_end, search.method, found))
+                        }).await
+                            .map_err(|e| Error::protocol(ErrorCode::InternalError, e.to_string()))?
+                            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        match found {
+                            Some(element) => {
+                                let body = json!({ "status": "found", "method": method, "steps": steps, "element": element });
+                                Ok(json!({
+                                    "content": [{"type": "text", "text": body.to_string()}]
+                                }))
+                            }
+                            None => {
+                                let body = json!({ "status": "not_found", "steps": steps, "reached_end": reached_end });
+                                Ok(json!({
+                                    "content": [{"type": "text", "text": body.to_string()}],
+                                    "isError": true
+                                }))
+                            }
+                        }
+                    }
                     "capture_screen" => {
                         let mut vision = self.vision.lock().await;
-                        let data = vision.capture_frame().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
-                        
-                        use image::{ImageBuffer, Rgba, ImageFormat};
-                        use std::io::Cursor;
+                        let mut data = vision.capture_frame().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let changes = vision.take_changes();
+                        let pointer = vision.pointer();
+                        if args["include_cursor"].as_bool().unwrap_or(true) {
+                            vision.composite_pointer(&mut data);
+                        }
+
+                        let (width, height) = vision.get_dimensions();
+                        drop(vision);
+                        // Before marks, so OCR marks never read redacted text.
+                        let redacted = self.redact(&mut data, width, height, [0, 0]).await?;
+                        let marks = self.collect_marks(&args, &data, width, height).await?;
+                        if let Some(step) = positive_u32(&args["grid"], "grid")? {
+                            grid::draw_grid(&mut data, width, height, &GridSpec { origin: [0, 0], scale: 1.0, step });
+                        }
+                        if let Some(marks) = &marks {
+                            marks::draw_marks(&mut data, width, height, marks);
+                        }
+                        let png = encode_png(&data, width, height, None)?;
+                        let mut content = vec![
+                            json!({"type": "text", "text": png}),
+                            json!({"type": "text", "text": json!({ "pointer": pointer, "changes_since_last_capture": changes, "redacted": redacted }).to_string()}),
+                        ];
+                        if let Some(marks) = marks {
+                            content.push(json!({"type": "text", "text": json!({ "marks": marks }).to_string()}));
+                            *self.marks.lock().unwrap() = marks;
+                        }
+                        Ok(json!({ "content": content }))
+                    }
+                    "configure_redaction" => {
+                        let strings = |name: &str| -> Vec<String> {
+                            args[name].as_array().map(|items| items.iter().filter_map(|i| i.as_str().map(String::from)).collect()).unwrap_or_default()
+                        };
+                        let rects = args["rects"].as_array().map(Vec::as_slice).unwrap_or_default().iter()
+                            .map(|value| {
+                                let region = parse_region(value)?.ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Rect must be an object"))?;
+                                let edges = [Some(region.x), Some(region.y), region.x.checked_add(region.width), region.y.checked_add(region.height)]
+                                    .map(|edge| edge.and_then(|e| i32::try_from(e).ok()));
+                                match edges {
+                                    [Some(left), Some(top), Some(right), Some(bottom)] => Ok([left, top, right, bottom]),
+                                    _ => Err(Error::protocol(ErrorCode::InvalidParams, format!("Rect must end within {} pixels", i32::MAX))),
+                                }
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        let additions = RedactionRules { window_titles: strings("window_titles"), processes: strings("processes"), rects, ..RedactionRules::default() };
+
+                        let mut rules = self.redactor.rules();
+                        if args["reset"].as_bool().unwrap_or(false) {
+                            *rules = self.startup_redaction.clone();
+                        }
+                        rules.extend(additions);
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&*rules).unwrap_or_default()}]
+                        }))
+                    }
+                    "zoom" => {
+                        if args["region"].is_null() {
+                            return Err(Error::protocol(ErrorCode::InvalidParams, "Missing region"));
+                        }
+                        let mut vision = self.vision.lock().await;
+                        vision.refresh().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let (width, height) = vision.get_dimensions();
+                        let region = screen_region(&args["region"], width, height)?;
+                        let mut crop = vision::region::crop(vision.frame(), width, region);
+                        drop(vision);
+                        let redacted = self.redact(&mut crop, region.width, region.height, [region.x, region.y]).await?;
+
+                        let scale = grid::zoom_scale(region.width, region.height, positive_u32(&args["scale"], "scale")?);
+                        let step = positive_u32(&args["grid_step"], "grid_step")?.unwrap_or_else(|| grid::auto_step(scale as f32, 60.0));
+                        // Nearest-neighbour keeps each screen pixel a crisp block, so pixel edges stay countable.
+                        let (zoomed_width, zoomed_height) = (region.width * scale, region.height * scale);
+                        let mut zoomed = image::imageops::resize(&crop, zoomed_width, zoomed_height, image::imageops::FilterType::Nearest).into_raw();
+                        grid::draw_grid(&mut zoomed, zoomed_width, zoomed_height, &GridSpec { origin: [region.x, region.y], scale: scale as f32, step });
+
+                        let mapping = format!("Image pixel (u, v) is screen ({} + u / {}, {} + v / {})", region.x, scale, region.y, scale);
+                        Ok(json!({
+                            "content": [
+                                {"type": "text", "text": encode_png(&zoomed, zoomed_width, zoomed_height, None)?},
+                                {"type": "text", "text": json!({ "region": [region.x, region.y, region.x + region.width, region.y + region.height], "scale": scale, "grid_step": step, "mapping": mapping, "redacted": redacted }).to_string()}
+                            ]
+                        }))
+                    }
+                    "click_mark" => {
+                        let executor = self.executor.as_ref().ok_or_else(|| {
+                            Error::protocol(ErrorCode::MethodNotFound, "Hardware executor is not available (driver missing)")
+                        })?;
+                        let id = args["mark"].as_u64().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing mark"))? as usize;
+                        let mark = self.marks.lock().unwrap().iter().find(|m| m.id == id).cloned()
+                            .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, format!("No mark {} in the last annotated screenshot", id)))?;
+
+                        let [x, y] = mark.center;
+                        executor.smooth_move(x, y, 10).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        executor.click(x, y).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        Ok(json!({
+                            "content": [{"type": "text", "text": format!("Clicked mark {} at {}, {}", id, x, y)}]
+                        }))
+                    }
+                    "ocr_screen" => {
+                        let region = parse_region(&args["region"])?;
+                        let scale = args["scale"].as_u64().unwrap_or(1) as u32;
+
+                        let mut vision = self.vision.lock().await;
+                        let mut data = vision.capture_frame().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let (width, height) = vision.get_dimensions();
+                        drop(vision);
+                        let redacted = self.redact(&mut data, width, height, [0, 0]).await?;
+
+                        let output = recognize_text(args["language"].as_str().map(String::from), data, width, height, region, scale).await?;
+                        let text: Vec<&str> = output.lines.iter().map(|line| line.text.as_str()).collect();
+                        Ok(json!({
+                            "content": [
+                                {"type": "text", "text": text.join("\n")},
+                                {"type": "text", "text": serde_json::to_string(&output).unwrap_or_default()},
+                                {"type": "text", "text": json!({ "redacted": redacted }).to_string()}
+                            ]
+                        }))
+                    }
+                    "find_image" => {
                         use base64::Engine;
 
+                        let encoded = args["template"].as_str().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing template"))?;
+                        let encoded = encoded.split_once("base64,").map_or(encoded, |(_, data)| data);
+                        let png = base64::engine::general_purpose::STANDARD.decode(encoded.trim())
+                            .map_err(|e| Error::protocol(ErrorCode::InvalidParams, format!("Template is not valid base64: {}", e)))?;
+                        let template = image::load_from_memory(&png)
+                            .map_err(|e| Error::protocol(ErrorCode::InvalidParams, format!("Template is not a valid image: {}", e)))?
+                            .to_rgba8();
+                        let template = Plane::from_rgba(template.as_raw(), template.width() as usize, template.height() as usize);
+
+                        let mut options = MatchOptions::default();
+                        if let Some(threshold) = args["threshold"].as_f64() {
+                            if !(-1.0..=1.0).contains(&threshold) {
+                                return Err(Error::protocol(ErrorCode::InvalidParams, "threshold must be between -1 and 1"));
+                            }
+                            options.threshold = threshold as f32;
+                        }
+                        if let Some(max_results) = args["max_results"].as_u64() {
+                            options.max_results = usize::try_from(max_results).ok().filter(|n| *n > 0)
+                                .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "max_results must be a positive integer"))?;
+                        }
+                        if let Some(scales) = args["scales"].as_array() {
+                            options.scales = scales.iter()
+                                .map(|s| s.as_f64().filter(|s| *s > 0.0 && *s <= MAX_TEMPLATE_SCALE).map(|s| s as f32))
+                                .collect::<Option<_>>()
+                                .filter(|scales: &Vec<f32>| !scales.is_empty())
+                                .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, format!("scales must be a non-empty list of numbers in (0, {}]", MAX_TEMPLATE_SCALE)))?;
+                        }
+
+                        let mut vision = self.vision.lock().await;
+                        let data = vision.capture_frame().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
                         let (width, height) = vision.get_dimensions();
-                        let img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(width, height, data)
-                            .ok_or_else(|| Error::protocol(ErrorCode::InternalError, "Failed to create image buffer"))?;
-                        
-                        let mut png_data = Vec::new();
-                        let mut cursor = Cursor::new(&mut png_data);
-                        img.write_to(&mut cursor, ImageFormat::Png).map_err(|e| Error::protocol(ErrorCode::InternalError, e.to_string()))?;
+                        drop(vision);
 
-                        let b64 = base64::engine::general_purpose::STANDARD.encode(png_data);
+                        let region = screen_region(&args["region"], width, height)?;
+
+                        // Matching is CPU-bound and can take a second on a full screen.
+                        let matches = tokio::task::spawn_blocking(move || {
+                            let frame = Plane::from_bgra(&data, width as usize, height as usize);
+                            let haystack = frame.crop(region.x as usize, region.y as usize, region.width as usize, region.height as usize);
+                            vision::template::find_template(&haystack, &template, &options)
+                        }).await.map_err(|e| Error::protocol(ErrorCode::InternalError, e.to_string()))?;
+
+                        let matches: Vec<_> = matches.into_iter().map(|m| {
+                            let [left, top, right, bottom] = m.rect;
+                            let (dx, dy) = (region.x as i32, region.y as i32);
+                            json!({
+                                "rect": [left + dx, top + dy, right + dx, bottom + dy],
+                                "center": [(left + right) / 2 + dx, (top + bottom) / 2 + dy],
+                                "score": m.score,
+                                "scale": m.scale
+                            })
+                        }).collect();
                         Ok(json!({
-                            "content": [{"type": "text", "text": format!("data:image/png;base64,{}", b64)}]
+                            "content": [{"type": "text", "text": json!({ "matches": matches }).to_string()}]
+                        }))
+                    }
+                    "get_pixels" => {
+                        let points = args["points"].as_array().map(Vec::as_slice).unwrap_or_default();
+                        let regions = args["regions"].as_array().map(Vec::as_slice).unwrap_or_default();
+                        if points.is_empty() && regions.is_empty() {
+                            return Err(Error::protocol(ErrorCode::InvalidParams, "Pass at least one point or region"));
+                        }
+                        if points.len() > MAX_PIXEL_POINTS || regions.len() > MAX_PIXEL_REGIONS {
+                            return Err(Error::protocol(ErrorCode::InvalidParams, format!("Pass at most {} points and {} regions", MAX_PIXEL_POINTS, MAX_PIXEL_REGIONS)));
+                        }
+
+                        let mut vision = self.vision.lock().await;
+                        vision.refresh().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let (width, height) = vision.get_dimensions();
+                        let points = points.iter().map(|point| {
+                            let coordinate = |name: &str| point[name].as_u64().and_then(|v| u32::try_from(v).ok());
+                            match (coordinate("x"), coordinate("y")) {
+                                (Some(x), Some(y)) if x < width && y < height => Ok(Region { x, y, width: 1, height: 1 }),
+                                _ => Err(Error::protocol(ErrorCode::InvalidParams, format!("Point {} is outside the {}x{} screen", point, width, height))),
+                            }
+                        }).collect::<Result<Vec<_>, Error>>()?;
+                        let regions = regions.iter().map(|region| screen_region(region, width, height)).collect::<Result<Vec<_>, Error>>()?;
+
+                        // Sample a redacted copy of the area, so pixels can't spell out what screenshots hide.
+                        let bounds = points.iter().chain(&regions).copied().reduce(Region::union).expect("at least one point or region");
+                        let mut area = vision::region::crop(vision.frame(), width, bounds).into_raw();
+                        drop(vision);
+                        let redacted = self.redact(&mut area, bounds.width, bounds.height, [bounds.x, bounds.y]).await?;
+                        let local = |r: &Region| Region { x: r.x - bounds.x, y: r.y - bounds.y, ..*r };
+
+                        let samples: Vec<_> = points.iter().map(|point| {
+                            let at = local(point);
+                            PixelSample { point: [point.x, point.y], ..pixels::sample(&area, bounds.width, at.x, at.y) }
+                        }).collect();
+                        let stats: Vec<_> = regions.iter().map(|region| RegionStats {
+                            rect: [region.x, region.y, region.x + region.width, region.y + region.height],
+                            ..pixels::region_stats(&area, bounds.width, local(region))
+                        }).collect();
+
+                        Ok(json!({
+                            "content": [{"type": "text", "text": json!({ "points": samples, "regions": stats, "redacted": redacted }).to_string()}]
+                        }))
+                    }
+                    "start_recording" => {
+                        let options = RecordingOptions::from_args(&args).map_err(|e| Error::protocol(ErrorCode::InvalidParams, e.to_string()))?;
+                        let mut slot = self.recording.lock().unwrap();
+                        if slot.as_ref().is_some_and(|recording| !recording.is_finished()) {
+                            return Err(Error::protocol(ErrorCode::InvalidRequest, "A recording is already running; call stop_recording first"));
+                        }
+                        let recording = Recording::start(self.vision.clone(), self.redactor.clone(), options)
+                            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        let text = format!("Recording to {} at {} fps", recording.options().path.display(), recording.options().fps);
+                        *slot = Some(recording);
+                        Ok(json!({
+                            "content": [{"type": "text", "text": text}]
+                        }))
+                    }
+                    "stop_recording" => {
+                        // Taken out of the mutex first: the guard must not be held across the await.
+                        let recording = self.recording.lock().unwrap().take()
+                            .ok_or_else(|| Error::protocol(ErrorCode::InvalidRequest, "No recording is running"))?;
+                        let summary = recording.stop().await.map_err(|e| Error::protocol(ErrorCode::InternalError, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&summary).unwrap_or_default()}],
+                            "isError": summary.error.is_some()
                         }))
                     }
                     "execute_click" => {
@@ -190,6 +1343,27 @@ impl ServerHandler for JarvisHandler {
                             "content": [{"type": "text", "text": format!("Clicked at {}, {}", x, y)}]
                         }))
                     }
+                    "list_windows" => {
+                        let windows = window_manager::filter_windows(self.windows.as_ref(), args["title"].as_str(), args["process_name"].as_str())
+                            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+                        Ok(json!({
+                            "content": [{"type": "text", "text": serde_json::to_string(&windows).unwrap_or_default()}]
+                        }))
+                    }
+                    "window_action" => {
+                        let handle = args["handle"].as_u64().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing handle"))?;
+                        let action = WindowAction::from_args(&args).map_err(|e| Error::protocol(ErrorCode::InvalidParams, e.to_string()))?;
+                        action.apply(self.windows.as_ref(), handle).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?;
+
+                        // Report the resulting state; a closed window may already be gone.
+                        let text = match window_manager::find_window(self.windows.as_ref(), handle) {
+                            Ok(window) => serde_json::to_string(&window).unwrap_or_default(),
+                            Err(_) => format!("Window {}: {} done", handle, args["action"].as_str().unwrap_or_default()),
+                        };
+                        Ok(json!({
+                            "content": [{"type": "text", "text": text}]
+                        }))
+                    }
                     "open_url" => {
                         let url = args["url"].as_str().ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, "Missing URL"))?;
                         std::process::Command::new("powershell")
@@ -217,9 +1391,297 @@ impl ServerHandler for JarvisHandler {
                         }))
                     }
                     _ => Err(Error::protocol(ErrorCode::MethodNotFound, format!("Tool {} not found", tool_name))),
+                };
+
+                match result {
+                    Ok(response) if DIALOG_AWARE_TOOLS.contains(&tool_name) => Ok(self.with_dialogs(&args, response).await),
+                    result => result,
                 }
             }
             _ => Err(Error::protocol(ErrorCode::MethodNotFound, format!("Method {} not found", method))),
         }
     }
 }
+
+/// Largest template scale find_image accepts; beyond it a template outgrows any screen.
+const MAX_TEMPLATE_SCALE: f64 = 8.0;
+/// Most points and regions one get_pixels call may sample.
+const MAX_PIXEL_POINTS: usize = 256;
+const MAX_PIXEL_REGIONS: usize = 32;
+
+/// Tools after which open modal dialogs are reported, since they block further input.
+const DIALOG_AWARE_TOOLS: &[&str] = &[
+    "get_screen_metadata",
+    "select_text",
+    "set_caret",
+    "table_cell",
+    "invoke_menu",
+    "dismiss_dialog",
+    "set_focus",
+    "scroll_into_view",
+    "find_by_scrolling",
+    "type_text",
+    "execute_click",
+    "click_mark",
+    "window_action",
+    "open_url",
+    "launch_app",
+];
+
+impl JarvisHandler {
+    /// Result of a screen wait: status and timing, plus the frame (or region) it ended on.
+    async fn screen_wait_response(&self, status: &str, report: WaitReport<serde_json::Value>, args: &serde_json::Value, region: Region) -> Result<serde_json::Value, Error> {
+        let elapsed_ms = report.elapsed.as_millis() as u64;
+        let (mut body, is_error) = match report.outcome {
+            WaitOutcome::Satisfied(details) => (details, false),
+            WaitOutcome::TimedOut => (json!({}), true),
+        };
+        body["status"] = json!(if is_error { "timeout" } else { status });
+        body["elapsed_ms"] = json!(elapsed_ms);
+        body["attempts"] = json!(report.attempts);
+
+        let mut content = Vec::new();
+        if args["include_image"].as_bool().unwrap_or(true) {
+            let vision = self.vision.lock().await;
+            let mut image = vision::region::crop(vision.frame(), vision.get_dimensions().0, region);
+            drop(vision);
+            body["redacted"] = json!(self.redact(&mut image, region.width, region.height, [region.x, region.y]).await?);
+            content.push(json!({"type": "text", "text": encode_png(&image, region.width, region.height, None)?}));
+        }
+        content.push(json!({"type": "text", "text": body.to_string()}));
+        Ok(json!({ "content": content, "isError": is_error }))
+    }
+
+    /// Blacks out whatever the redaction rules cover in an image of the screen whose top-left
+    /// pixel is at `origin`, and returns the audit note for the tool result.
+    async fn redact(&self, image: &mut [u8], width: u32, height: u32, origin: [u32; 2]) -> Result<Vec<Redaction>, Error> {
+        // The lookup makes cross-process UIA calls; only the cheap blackout runs here.
+        let redactor = self.redactor.clone();
+        let found = tokio::task::spawn_blocking(move || redactor.locate(origin, width, height)).await
+            .map_err(|e| Error::protocol(ErrorCode::InternalError, e.to_string()))?
+            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, format!("Redaction failed, image withheld: {}", e)))?;
+        redaction::apply(image, width, height, origin, &found);
+        Ok(found)
+    }
+
+    /// Marks requested by `capture_screen`'s `marks` argument, numbered from 1.
+    async fn collect_marks(&self, args: &serde_json::Value, frame: &[u8], width: u32, height: u32) -> Result<Option<Vec<Mark>>, Error> {
+        let max_marks = args["max_marks"].as_u64().unwrap_or(150) as usize;
+        let marks = match args["marks"].as_str().unwrap_or("none") {
+            "none" => return Ok(None),
+            "ui" => {
+                let root = match args["window"].as_str() {
+                    Some(title) => self.inspector.find_window(title)
+                        .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?
+                        .ok_or_else(|| Error::protocol(ErrorCode::RequestFailed, format!("Window not found: {}", title)))?,
+                    None => self.inspector.foreground_window().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?,
+                };
+                self.inspector.interactive_elements(&root, max_marks)
+                    .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, element)| Mark { element: Some(element.clone()), ..Mark::new(i + 1, element.rect, "ui") })
+                    .collect()
+            }
+            "ocr" => {
+                let output = recognize_text(None, frame.to_vec(), width, height, None, 1).await?;
+                output.lines.into_iter()
+                    .flat_map(|line| line.words)
+                    .take(max_marks)
+                    .enumerate()
+                    .map(|(i, word)| Mark { text: Some(word.text), ..Mark::new(i + 1, word.rect, "ocr") })
+                    .collect()
+            }
+            other => return Err(Error::protocol(ErrorCode::InvalidParams, format!("Unknown marks source: {}", other))),
+        };
+        Ok(Some(marks))
+    }
+
+    /// Puts a warning about modal dialogs of the target app in front of a tool result.
+    /// Detection walks each dialog's descendants, so it runs on a blocking thread.
+    async fn with_dialogs(&self, args: &serde_json::Value, mut response: serde_json::Value) -> serde_json::Value {
+        let Ok(process_id) = self.target_process(args) else { return response };
+        let (windows, inspector) = (self.windows.clone(), self.inspector.clone());
+        let detected = tokio::task::spawn_blocking(move || dialogs::detect(windows.as_ref(), &inspector, Some(process_id), true)).await;
+        let dialogs = match detected {
+            Ok(Ok(dialogs)) if !dialogs.is_empty() => dialogs,
+            _ => return response,
+        };
+        let warning = json!({
+            "type": "text",
+            "text": format!(
+                "MODAL DIALOG OPEN: input to the owning window is blocked until it is dismissed (see dismiss_dialog). {}",
+                json!({ "modal_dialogs": dialogs })
+            )
+        });
+        if let Some(content) = response["content"].as_array_mut() {
+            content.insert(0, warning);
+        }
+        response
+    }
+
+    /// Process of the app a tool targets: the window named by the `window` argument, or
+    /// the foreground window (which is the dialog itself while one is blocking input).
+    fn target_process(&self, args: &serde_json::Value) -> Result<u32, Error> {
+        let window = match args["window"].as_str() {
+            Some(title) => self.inspector.find_window(title)
+                .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?
+                .ok_or_else(|| Error::protocol(ErrorCode::RequestFailed, format!("Window not found: {}", title)))?,
+            None => self.inspector.foreground_window().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?,
+        };
+        self.inspector.process_id(&window).map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))
+    }
+
+    /// Element matching the `selector` argument, or the focused element when it is omitted.
+    fn resolve_target(&self, selector: &serde_json::Value) -> Result<IUIAutomationElement, Error> {
+        if selector.is_null() {
+            return self.inspector.focused().map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()));
+        }
+        let selector = parse_selector(selector)?;
+        self.inspector.find_first(&selector)
+            .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))?
+            .ok_or_else(|| Error::protocol(ErrorCode::RequestFailed, "No element matches the selector"))
+    }
+}
+
+fn selector_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" },
+            "name_contains": { "type": "string" },
+            "control_type": { "type": "string" },
+            "automation_id": { "type": "string" },
+            "class_name": { "type": "string" }
+        }
+    })
+}
+
+fn properties_schema() -> serde_json::Value {
+    let names: Vec<&str> = Property::ALL.iter().map(|p| p.name()).collect();
+    json!({
+        "description": "Дополнительные свойства: пресет (basic, interactive, full) или список имен",
+        "oneOf": [
+            { "type": "string", "enum": ["basic", "interactive", "full"] },
+            { "type": "array", "items": { "type": "string", "enum": names } }
+        ],
+        "default": "basic"
+    })
+}
+
+fn parse_selector(value: &serde_json::Value) -> Result<Selector, Error> {
+    let selector: Selector = serde_json::from_value(value.clone())
+        .map_err(|e| Error::protocol(ErrorCode::InvalidParams, format!("Invalid selector: {}", e)))?;
+    if selector.is_empty() {
+        return Err(Error::protocol(ErrorCode::InvalidParams, "Selector must set at least one field"));
+    }
+    Ok(selector)
+}
+
+fn region_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "x": { "type": "integer" },
+            "y": { "type": "integer" },
+            "width": { "type": "integer" },
+            "height": { "type": "integer" }
+        },
+        "required": ["x", "y", "width", "height"]
+    })
+}
+
+/// Optional screen region; `null` means the whole screen.
+fn parse_region(value: &serde_json::Value) -> Result<Option<Region>, Error> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let field = |name: &str| {
+        value[name].as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, format!("Region needs a non-negative {}", name)))
+    };
+    Ok(Some(Region { x: field("x")?, y: field("y")?, width: field("width")?, height: field("height")? }))
+}
+
+/// Optional integer argument that must be at least 1 and fit in a `u32`.
+fn positive_u32(value: &serde_json::Value, name: &str) -> Result<Option<u32>, Error> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    value.as_u64()
+        .and_then(|v| u32::try_from(v).ok())
+        .filter(|v| *v > 0)
+        .map(Some)
+        .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, format!("{} must be a positive integer up to {}", name, u32::MAX)))
+}
+
+/// Optional `region` argument checked against the screen; defaults to the whole screen.
+fn screen_region(value: &serde_json::Value, width: u32, height: u32) -> Result<Region, Error> {
+    let region = parse_region(value)?.unwrap_or(Region { x: 0, y: 0, width, height });
+    if region.width == 0
+        || region.height == 0
+        || region.x.checked_add(region.width).is_none_or(|right| right > width)
+        || region.y.checked_add(region.height).is_none_or(|bottom| bottom > height)
+    {
+        return Err(Error::protocol(ErrorCode::InvalidParams, format!("Region is empty or outside the {}x{} screen", width, height)));
+    }
+    Ok(region)
+}
+
+/// Recognizes text on a blocking thread, since the OCR engine waits on WinRT async operations.
+async fn recognize_text(language: Option<String>, frame: Vec<u8>, width: u32, height: u32, region: Option<Region>, scale: u32) -> Result<OcrOutput, Error> {
+    tokio::task::spawn_blocking(move || TextRecognizer::new(language.as_deref())?.recognize(&frame, width, height, region, scale))
+        .await
+        .map_err(|e| Error::protocol(ErrorCode::InternalError, e.to_string()))?
+        .map_err(|e| Error::protocol(ErrorCode::RequestFailed, e.to_string()))
+}
+
+/// Encodes a BGRA frame, or a region of it, as a PNG data URL.
+fn encode_png(bgra: &[u8], width: u32, height: u32, region: Option<Region>) -> Result<String, Error> {
+    use base64::Engine;
+
+    let mut image = vision::region::crop(bgra, width, region.unwrap_or(Region { x: 0, y: 0, width, height }));
+    for pixel in image.pixels_mut() {
+        pixel.0.swap(0, 2);
+    }
+    let mut png = Vec::new();
+    image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| Error::protocol(ErrorCode::InternalError, e.to_string()))?;
+    Ok(format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(png)))
+}
+
+/// Identifies a `get_screen_metadata` snapshot: diffs only compare trees walked the same way.
+fn snapshot_key(scope: &str, options: &TraversalOptions) -> String {
+    format!(
+        "{}|depth={}|properties={:?}|mode={}|nodes={}|children={}|timeout={:?}",
+        scope, options.max_depth, options.properties, options.mode.name(), options.max_nodes, options.max_children, options.timeout
+    )
+}
+
+fn parse_format(value: &serde_json::Value) -> Result<OutputFormat, Error> {
+    match value.as_str() {
+        None => Ok(OutputFormat::Json),
+        Some(name) => OutputFormat::parse(name)
+            .ok_or_else(|| Error::protocol(ErrorCode::InvalidParams, format!("Unknown format: {}", name))),
+    }
+}
+
+fn wait_response(state: &str, report: WaitReport<Option<UiElement>>) -> serde_json::Value {
+    let elapsed_ms = report.elapsed.as_millis() as u64;
+    match report.outcome {
+        WaitOutcome::Satisfied(element) => {
+            let body = json!({ "status": "matched", "state": state, "elapsed_ms": elapsed_ms, "attempts": report.attempts, "element": element });
+            json!({
+                "content": [{"type": "text", "text": body.to_string()}]
+            })
+        }
+        WaitOutcome::TimedOut => {
+            let body = json!({ "status": "timeout", "state": state, "elapsed_ms": elapsed_ms, "attempts": report.attempts });
+            json!({
+                "content": [{"type": "text", "text": body.to_string()}],
+                "isError": true
+            })
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use windows::Win32::UI::Accessibility::*;
+use windows::core::BSTR;
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use crate::executor::{self, HardwareExecutor};
+use crate::inspector::{Selector, UiInspector};
+
+/// How long a submenu may take to appear after its parent was expanded.
+const SUBMENU_TIMEOUT: Duration = Duration::from_millis(2000);
+const SUBMENU_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Serialize, Debug)]
+pub struct MenuStep {
+    pub segment: String,
+    /// "expand", "invoke" or "access_key".
+    pub method: &'static str,
+}
+
+/// Where and why a menu path could not be followed.
+#[derive(Serialize, Debug)]
+pub struct MenuFailure {
+    pub failed_index: usize,
+    pub segment: String,
+    pub reason: String,
+    /// Items that were visible at the failing level.
+    pub available: Vec<String>,
+    pub completed: Vec<MenuStep>,
+}
+
+/// Walks a menu path such as `["File", "Save As..."]`, starting from the menu bar
+/// of `root` or from a context menu that is already open.
+///
+/// Runs synchronously and blocks during submenu waits: UIA elements are not `Send`,
+/// so async callers should run the whole walk on a blocking thread.
+pub fn invoke_path(
+    inspector: &UiInspector,
+    executor: Option<&HardwareExecutor>,
+    root: &IUIAutomationElement,
+    path: &[String],
+) -> Result<Vec<MenuStep>, MenuFailure> {
+    let mut completed = Vec::new();
+    let mut parent: Option<IUIAutomationElement> = None;
+
+    for (index, segment) in path.iter().enumerate() {
+        let fail = |completed: Vec<MenuStep>, reason: String, available: Vec<String>| MenuFailure {
+            failed_index: index,
+            segment: segment.clone(),
+            reason,
+            available,
+            completed,
+        };
+
+        // Submenus are populated asynchronously once their parent expands.
+        let timeout = if index == 0 { Duration::ZERO } else { SUBMENU_TIMEOUT };
+        let Some(item) = wait_for_item(inspector, root, parent.as_ref(), segment, timeout) else {
+            let available = visible_items(inspector, root, parent.as_ref());
+            return Err(fail(completed, "No menu item with this name".to_string(), available));
+        };
+        if !inspector.is_enabled(&item) {
+            return Err(fail(completed, "Menu item is disabled".to_string(), Vec::new()));
+        }
+
+        let last = index + 1 == path.len();
+        match activate(inspector, executor, &item, last, index == 0) {
+            Ok(method) => completed.push(MenuStep { segment: segment.clone(), method }),
+            Err(e) => return Err(fail(completed, e.to_string(), Vec::new())),
+        }
+        parent = Some(item);
+    }
+    Ok(completed)
+}
+
+/// Expands intermediate items and invokes the last one, falling back to its access key.
+fn activate(
+    inspector: &UiInspector,
+    executor: Option<&HardwareExecutor>,
+    item: &IUIAutomationElement,
+    last: bool,
+    in_menu_bar: bool,
+) -> Result<&'static str> {
+    if !last
+        && let Ok(pattern) = unsafe { item.GetCurrentPatternAs::<IUIAutomationExpandCollapsePattern>(UIA_ExpandCollapsePatternId) }
+        && unsafe { pattern.Expand() }.is_ok()
+    {
+        return Ok("expand");
+    }
+    if inspector.invoke(item).is_ok() {
+        return Ok("invoke");
+    }
+
+    let executor = executor.ok_or_else(|| anyhow!("Item has no usable pattern and the hardware executor is not available"))?;
+    let key = access_key(item).ok_or_else(|| anyhow!("Item has no usable pattern and no access key"))?;
+    let code = executor::scan_code(key).ok_or_else(|| anyhow!("Unsupported access key '{}'", key))?;
+    if in_menu_bar {
+        executor.press_keys(&[executor::SCAN_ALT, code])?;
+    } else {
+        executor.press_keys(&[code])?;
+    }
+    Ok("access_key")
+}
+
+fn wait_for_item(
+    inspector: &UiInspector,
+    root: &IUIAutomationElement,
+    parent: Option<&IUIAutomationElement>,
+    segment: &str,
+    timeout: Duration,
+) -> Option<IUIAutomationElement> {
+    let started = Instant::now();
+    loop {
+        if let Some(item) = find_item(inspector, root, parent, segment) {
+            return Some(item);
+        }
+        if started.elapsed() >= timeout {
+            return None;
+        }
+        std::thread::sleep(SUBMENU_INTERVAL);
+    }
+}
+
+/// Looks in open popup menus first, then under the previous item, then in the window itself.
+fn find_item(
+    inspector: &UiInspector,
+    root: &IUIAutomationElement,
+    parent: Option<&IUIAutomationElement>,
+    segment: &str,
+) -> Option<IUIAutomationElement> {
+    let wanted = normalize(segment);
+    search_scopes(inspector, root, parent)
+        .iter()
+        .flat_map(|scope| menu_items(inspector, scope))
+        .find(|item| normalize(&item_name(item)) == wanted)
+}
+
+fn visible_items(inspector: &UiInspector, root: &IUIAutomationElement, parent: Option<&IUIAutomationElement>) -> Vec<String> {
+    let mut names: Vec<String> = search_scopes(inspector, root, parent)
+        .iter()
+        .flat_map(|scope| menu_items(inspector, scope))
+        .filter(|item| !unsafe { item.CurrentIsOffscreen() }.map(|b| b.as_bool()).unwrap_or(false))
+        .map(|item| item_name(&item))
+        .filter(|name| !name.is_empty())
+        .collect();
+    // The same item can be reached from several scopes; keep the first occurrence in menu order.
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
+
+fn search_scopes(inspector: &UiInspector, root: &IUIAutomationElement, parent: Option<&IUIAutomationElement>) -> Vec<IUIAutomationElement> {
+    // Win32 popup menus are top-level windows of their own; WPF ones live under the window.
+    // Only popups of the target's process count, so another app's menu is never used.
+    let menus = Selector { control_type: Some("menu".to_string()), ..Selector::default() };
+    let process_id = unsafe { root.CurrentProcessId() }.ok();
+    let mut scopes: Vec<IUIAutomationElement> = unsafe { inspector.automation().GetRootElement() }
+        .ok()
+        .and_then(|desktop| inspector.find_in(&desktop, TreeScope_Children, &menus).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|menu| process_id.is_some() && unsafe { menu.CurrentProcessId() }.ok() == process_id)
+        .collect();
+    scopes.extend(parent.cloned());
+    scopes.push(root.clone());
+    scopes
+}
+
+fn menu_items(inspector: &UiInspector, scope: &IUIAutomationElement) -> Vec<IUIAutomationElement> {
+    let items = Selector { control_type: Some("menuitem".to_string()), ..Selector::default() };
+    inspector.find_in(scope, TreeScope_Descendants, &items).unwrap_or_default()
+}
+
+fn item_name(item: &IUIAutomationElement) -> String {
+    unsafe { item.CurrentName().unwrap_or(BSTR::from("")).to_string() }
+}
+
+/// Compares names the way a user reads them: without mnemonics, shortcut text,
+/// trailing ellipsis or case ("&Save As...\tCtrl+Shift+S" == "save as").
+fn normalize(name: &str) -> String {
+    let label = name.split('\t').next().unwrap_or_default().replace('&', "");
+    label.trim().trim_end_matches("...").trim_end_matches('…').trim().to_lowercase()
+}
+
+/// The mnemonic letter from UIA's AccessKey ("Alt+F" or "F").
+fn access_key(item: &IUIAutomationElement) -> Option<char> {
+    let key = unsafe { item.CurrentAccessKey().ok()? }.to_string();
+    let letter = key.rsplit('+').next()?.trim();
+    let mut chars = letter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
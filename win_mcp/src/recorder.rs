@@ -0,0 +1,527 @@
+use anyhow::{Result, Context, anyhow};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageFormat, RgbaImage, imageops};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use crate::redaction::{self, Redaction, Redactor};
+use crate::vision::DesktopCapture;
+
+const DEFAULT_FPS: u32 = 5;
+const MAX_FPS: u32 = 30;
+const DEFAULT_MAX_DURATION_S: u64 = 60;
+const DEFAULT_MAX_SIZE_MB: u64 = 200;
+/// Frames waiting for the encoder; when it falls behind, new frames are dropped.
+const ENCODER_QUEUE: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingFormat {
+    /// Animated GIF, looping.
+    Gif,
+    /// A directory of numbered PNG files plus a `frames.json` manifest with timestamps.
+    Frames,
+}
+
+impl RecordingFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "gif" => Some(Self::Gif),
+            "frames" => Some(Self::Frames),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordingOptions {
+    pub path: PathBuf,
+    pub format: RecordingFormat,
+    pub fps: u32,
+    pub max_duration: Duration,
+    pub max_bytes: u64,
+    /// Downscale factor in (0, 1]; GIFs of a full screen are large and slow to encode.
+    pub scale: f32,
+}
+
+impl RecordingOptions {
+    fn new(path: PathBuf, format: Option<RecordingFormat>) -> Self {
+        let format = format.unwrap_or_else(|| {
+            if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif")) { RecordingFormat::Gif } else { RecordingFormat::Frames }
+        });
+        RecordingOptions {
+            path,
+            format,
+            fps: DEFAULT_FPS,
+            max_duration: Duration::from_secs(DEFAULT_MAX_DURATION_S),
+            max_bytes: DEFAULT_MAX_SIZE_MB * 1024 * 1024,
+            scale: 1.0,
+        }
+    }
+
+    /// Reads `start_recording` tool arguments.
+    pub fn from_args(args: &Value) -> Result<Self> {
+        let path = args["path"].as_str().ok_or_else(|| anyhow!("Missing path"))?;
+        let format = args["format"].as_str()
+            .map(|name| RecordingFormat::parse(name).ok_or_else(|| anyhow!("Unknown format: {}", name)))
+            .transpose()?;
+
+        let mut options = Self::new(PathBuf::from(path), format);
+        if let Some(fps) = args["fps"].as_u64() {
+            options.fps = frame_rate(fps)?;
+        }
+        if let Some(seconds) = args["max_duration_s"].as_f64() {
+            options.max_duration = time_limit(seconds)?;
+        }
+        if let Some(mb) = args["max_size_mb"].as_u64() {
+            options.max_bytes = size_limit(mb);
+        }
+        if let Some(scale) = args["scale"].as_f64() {
+            options.scale = scale as f32;
+        }
+        options.validated()
+    }
+
+    /// Reads `record <path> [--format gif|frames] [--fps N] [--duration SECONDS] [--max-size-mb N] [--scale F]`.
+    pub fn from_cli(args: &[String]) -> Result<Self> {
+        let mut path = None;
+        let mut format = None;
+        let mut fps = None;
+        let mut duration = None;
+        let mut max_mb = None;
+        let mut scale = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |flag: &str| iter.next().cloned().ok_or_else(|| anyhow!("{} needs a value", flag));
+            match arg.as_str() {
+                "--format" => format = Some(RecordingFormat::parse(&value(arg)?).ok_or_else(|| anyhow!("Unknown format"))?),
+                "--fps" => fps = Some(value(arg)?.parse::<u64>().context("Invalid --fps")?),
+                "--duration" => duration = Some(value(arg)?.parse::<f64>().context("Invalid --duration")?),
+                "--max-size-mb" => max_mb = Some(value(arg)?.parse::<u64>().context("Invalid --max-size-mb")?),
+                "--scale" => scale = Some(value(arg)?.parse().context("Invalid --scale")?),
+                flag if flag.starts_with("--") => return Err(anyhow!("Unknown option {}", flag)),
+                _ if path.is_none() => path = Some(PathBuf::from(arg)),
+                extra => return Err(anyhow!("Unexpected argument {}", extra)),
+            }
+        }
+
+        let path = path.ok_or_else(|| anyhow!("Usage: win_mcp record <path> [--format gif|frames] [--fps N] [--duration SECONDS] [--max-size-mb N] [--scale F]"))?;
+        let mut options = Self::new(path, format);
+        if let Some(fps) = fps {
+            options.fps = frame_rate(fps)?;
+        }
+        if let Some(seconds) = duration {
+            options.max_duration = time_limit(seconds)?;
+        }
+        options.max_bytes = max_mb.map_or(options.max_bytes, size_limit);
+        options.scale = scale.unwrap_or(options.scale);
+        options.validated()
+    }
+
+    fn validated(self) -> Result<Self> {
+        if self.fps == 0 || self.fps > MAX_FPS {
+            return Err(anyhow!("fps must be between 1 and {}", MAX_FPS));
+        }
+        if !(self.scale > 0.0 && self.scale <= 1.0) {
+            return Err(anyhow!("scale must be in (0, 1]"));
+        }
+        if self.max_duration.is_zero() || self.max_bytes == 0 {
+            return Err(anyhow!("max duration and size must be positive"));
+        }
+        Ok(self)
+    }
+}
+
+fn frame_rate(fps: u64) -> Result<u32> {
+    u32::try_from(fps).map_err(|_| anyhow!("fps must be between 1 and {}", MAX_FPS))
+}
+
+fn time_limit(seconds: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(seconds).map_err(|_| anyhow!("max duration must be a non-negative number of seconds"))
+}
+
+/// Size limit in bytes; absurdly large limits saturate rather than wrap around to a tiny one.
+fn size_limit(mb: u64) -> u64 {
+    mb.saturating_mul(1024 * 1024)
+}
+
+#[derive(Serialize, Debug)]
+pub struct RecordingSummary {
+    pub path: PathBuf,
+    pub format: RecordingFormat,
+    pub frames: u64,
+    pub dropped_frames: u64,
+    /// Frames with at least one area blacked out; `frames.json` lists the areas per frame.
+    pub redacted_frames: u64,
+    pub duration_ms: u64,
+    pub bytes: u64,
+    /// "stopped", "max_duration", "max_size" or "error".
+    pub ended_by: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A recording in progress: an async capture loop feeding a blocking encoder thread.
+pub struct Recording {
+    options: RecordingOptions,
+    stop: Arc<AtomicBool>,
+    task: tokio::task::JoinHandle<RecordingSummary>,
+}
+
+impl Recording {
+    /// Every frame goes through `redactor` before it is queued, like any other image the server produces.
+    pub fn start(vision: Arc<Mutex<DesktopCapture>>, redactor: Redactor, options: RecordingOptions) -> Result<Self> {
+        let sink = FrameSink::create(&options)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let task = tokio::spawn(record(vision, redactor, options.clone(), sink, stop.clone()));
+        Ok(Recording { options, stop, task })
+    }
+
+    pub fn options(&self) -> &RecordingOptions {
+        &self.options
+    }
+
+    /// Whether the recording ended on its own (limit reached or error).
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stops capturing, waits for the encoder to flush and returns the summary.
+    pub async fn stop(self) -> Result<RecordingSummary> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.task.await.context("Recording task failed")
+    }
+}
+
+struct CapturedFrame {
+    image: RgbaImage,
+    timestamp_ms: u64,
+    redactions: Vec<Redaction>,
+}
+
+async fn record(vision: Arc<Mutex<DesktopCapture>>, redactor: Redactor, options: RecordingOptions, sink: FrameSink, stop: Arc<AtomicBool>) -> RecordingSummary {
+    let bytes = sink.bytes.clone();
+    let (sender, receiver) = std::sync::mpsc::sync_channel::<CapturedFrame>(ENCODER_QUEUE);
+    let encoder = tokio::task::spawn_blocking(move || sink.run(receiver));
+
+    let started = Instant::now();
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / options.fps as f64));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut dropped = 0;
+
+    let (mut ended_by, mut error) = loop {
+        ticker.tick().await;
+        if stop.load(Ordering::SeqCst) {
+            break ("stopped", None);
+        }
+        if started.elapsed() >= options.max_duration {
+            break ("max_duration", None);
+        }
+        if bytes.load(Ordering::SeqCst) >= options.max_bytes {
+            break ("max_size", None);
+        }
+
+        let frame = match capture(&vision, &redactor, options.scale).await {
+            Ok((image, redactions)) => CapturedFrame { image, timestamp_ms: started.elapsed().as_millis() as u64, redactions },
+            // A frame that could not be checked is never written.
+            Err(e) => break ("error", Some(e.to_string())),
+        };
+        if !enqueue(&sender, frame, &mut dropped) {
+            break ("error", Some("Encoder stopped".to_string()));
+        }
+    };
+
+    drop(sender);
+    let (frames, redacted_frames) = match encoder.await {
+        Ok(Ok(counts)) => counts,
+        Ok(Err(e)) => {
+            (ended_by, error) = ("error", Some(e.to_string()));
+            (0, 0)
+        }
+        Err(e) => {
+            (ended_by, error) = ("error", Some(e.to_string()));
+            (0, 0)
+        }
+    };
+
+    RecordingSummary {
+        path: options.path,
+        format: options.format,
+        frames,
+        dropped_frames: dropped,
+        redacted_frames,
+        duration_ms: started.elapsed().as_millis() as u64,
+        bytes: bytes.load(Ordering::SeqCst),
+        ended_by,
+        error,
+    }
+}
+
+/// Queues a frame unless the encoder is behind; false once the encoder has gone away.
+fn enqueue(sender: &SyncSender<CapturedFrame>, frame: CapturedFrame, dropped: &mut u64) -> bool {
+    match sender.try_send(frame) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            *dropped += 1;
+            true
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    }
+}
+
+/// Current screen with the pointer and redactions applied, as RGBA, optionally downscaled.
+async fn capture(vision: &Mutex<DesktopCapture>, redactor: &Redactor, scale: f32) -> Result<(RgbaImage, Vec<Redaction>)> {
+    let mut vision = vision.lock().await;
+    let mut data = vision.capture_frame()?;
+    vision.composite_pointer(&mut data);
+    let (width, height) = vision.get_dimensions();
+    drop(vision);
+
+    let locator = redactor.clone();
+    let redactions = tokio::task::spawn_blocking(move || locator.locate([0, 0], width, height)).await
+        .context("Redaction task failed")?
+        .context("Redaction failed, recording stopped")?;
+    redaction::apply(&mut data, width, height, [0, 0], &redactions);
+
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    let image = RgbaImage::from_raw(width, height, data).ok_or_else(|| anyhow!("Frame buffer does not match its dimensions"))?;
+    if scale >= 1.0 {
+        return Ok((image, redactions));
+    }
+    let (w, h) = (((width as f32 * scale) as u32).max(1), ((height as f32 * scale) as u32).max(1));
+    Ok((imageops::resize(&image, w, h, imageops::FilterType::Triangle), redactions))
+}
+
+enum SinkTarget {
+    /// A GIF frame's delay depends on when the next one was captured, so the latest frame
+    /// waits in `pending` until then.
+    Gif { encoder: GifEncoder<BufWriter<CountingFile>>, pending: Option<CapturedFrame> },
+    Frames { directory: PathBuf, manifest: Vec<Value> },
+}
+
+/// Encoder side of a recording; runs on a blocking thread.
+struct FrameSink {
+    target: SinkTarget,
+    fps: u32,
+    bytes: Arc<AtomicU64>,
+}
+
+impl FrameSink {
+    fn create(options: &RecordingOptions) -> Result<Self> {
+        let bytes = Arc::new(AtomicU64::new(0));
+        let target = match options.format {
+            RecordingFormat::Gif => {
+                let file = File::create(&options.path).with_context(|| format!("Failed to create {}", options.path.display()))?;
+                let mut encoder = GifEncoder::new_with_speed(BufWriter::new(CountingFile { file, bytes: bytes.clone() }), 10);
+                encoder.set_repeat(Repeat::Infinite)?;
+                SinkTarget::Gif { encoder, pending: None }
+            }
+            RecordingFormat::Frames => {
+                std::fs::create_dir_all(&options.path).with_context(|| format!("Failed to create {}", options.path.display()))?;
+                SinkTarget::Frames { directory: options.path.clone(), manifest: Vec::new() }
+            }
+        };
+        Ok(FrameSink { target, fps: options.fps, bytes })
+    }
+
+    /// Encodes frames until the sender hangs up; returns how many were written and how many of those were redacted.
+    fn run(mut self, frames: std::sync::mpsc::Receiver<CapturedFrame>) -> Result<(u64, u64)> {
+        let (mut written, mut redacted) = (0, 0);
+        for frame in frames {
+            if !frame.redactions.is_empty() {
+                redacted += 1;
+            }
+            self.write(frame, written)?;
+            written += 1;
+        }
+        self.finish()?;
+        Ok((written, redacted))
+    }
+
+    fn write(&mut self, frame: CapturedFrame, index: u64) -> Result<()> {
+        match &mut self.target {
+            SinkTarget::Gif { encoder, pending } => {
+                let next_ms = frame.timestamp_ms;
+                if let Some(previous) = pending.replace(frame) {
+                    let delay = gif_delay_cs(previous.timestamp_ms, next_ms);
+                    encode_gif_frame(encoder, previous, delay)?;
+                }
+            }
+            SinkTarget::Frames { directory, manifest } => {
+                let name = format!("frame_{:05}.png", index);
+                let path = directory.join(&name);
+                frame.image.save_with_format(&path, ImageFormat::Png).with_context(|| format!("Failed to write {}", path.display()))?;
+                let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                self.bytes.fetch_add(size, Ordering::SeqCst);
+                manifest.push(serde_json::json!({ "file": name, "timestamp_ms": frame.timestamp_ms, "redacted": frame.redactions }));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.target {
+            SinkTarget::Gif { mut encoder, pending } => {
+                // Nothing follows the last frame; show it for one frame interval.
+                if let Some(last) = pending {
+                    let delay = gif_delay_cs(last.timestamp_ms, last.timestamp_ms + 1000 / self.fps as u64);
+                    encode_gif_frame(&mut encoder, last, delay)?;
+                }
+                // Dropping the encoder writes the GIF trailer; the BufWriter then flushes.
+                drop(encoder);
+                Ok(())
+            }
+            SinkTarget::Frames { directory, manifest } => {
+                let manifest = serde_json::json!({ "fps": self.fps, "frames": manifest });
+                std::fs::write(directory.join("frames.json"), serde_json::to_vec_pretty(&manifest)?).context("Failed to write frames.json")
+            }
+        }
+    }
+}
+
+fn encode_gif_frame(encoder: &mut GifEncoder<BufWriter<CountingFile>>, frame: CapturedFrame, delay_cs: u32) -> Result<()> {
+    let delay = Delay::from_numer_denom_ms(delay_cs * 10, 1);
+    encoder.encode_frame(Frame::from_parts(frame.image, 0, 0, delay)).context("Failed to encode GIF frame")
+}
+
+/// GIF delay, in centiseconds, between frames captured at `from_ms` and `to_ms`. Both
+/// timestamps are rounded first so rounding errors do not add up over a long recording;
+/// viewers stretch delays under 2 cs, so that is the minimum.
+fn gif_delay_cs(from_ms: u64, to_ms: u64) -> u32 {
+    let centiseconds = |ms: u64| (ms + 5) / 10;
+    centiseconds(to_ms).saturating_sub(centiseconds(from_ms)).clamp(2, u32::MAX as u64) as u32
+}
+
+/// File writer that tracks the number of bytes written for the size cap.
+struct CountingFile {
+    file: File,
+    bytes: Arc<AtomicU64>,
+}
+
+impl Write for CountingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.bytes.fetch_add(written as u64, Ordering::SeqCst);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cli(args: &[&str]) -> Result<RecordingOptions> {
+        RecordingOptions::from_cli(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn args_use_defaults_and_infer_format() {
+        let options = RecordingOptions::from_args(&json!({ "path": "out.GIF" })).unwrap();
+        assert_eq!(options.format, RecordingFormat::Gif);
+        assert_eq!(options.fps, DEFAULT_FPS);
+        assert_eq!(options.max_duration, Duration::from_secs(DEFAULT_MAX_DURATION_S));
+        assert_eq!(options.max_bytes, DEFAULT_MAX_SIZE_MB * 1024 * 1024);
+
+        let options = RecordingOptions::from_args(&json!({ "path": "out" })).unwrap();
+        assert_eq!(options.format, RecordingFormat::Frames);
+    }
+
+    #[test]
+    fn args_override_every_option() {
+        let args = json!({ "path": "out.gif", "format": "frames", "fps": 10, "max_duration_s": 2.5, "max_size_mb": 3, "scale": 0.5 });
+        let options = RecordingOptions::from_args(&args).unwrap();
+        assert_eq!(options.format, RecordingFormat::Frames);
+        assert_eq!(options.fps, 10);
+        assert_eq!(options.max_duration, Duration::from_millis(2500));
+        assert_eq!(options.max_bytes, 3 * 1024 * 1024);
+        assert_eq!(options.scale, 0.5);
+    }
+
+    #[test]
+    fn args_reject_invalid_values() {
+        for args in [
+            json!({}),
+            json!({ "path": "out", "format": "mp4" }),
+            json!({ "path": "out", "fps": 0 }),
+            json!({ "path": "out", "fps": 31 }),
+            json!({ "path": "out", "fps": u64::from(u32::MAX) + 1 }),
+            json!({ "path": "out", "max_duration_s": 0 }),
+            json!({ "path": "out", "max_duration_s": -1.0 }),
+            json!({ "path": "out", "max_duration_s": 1e300 }),
+            json!({ "path": "out", "max_size_mb": 0 }),
+            json!({ "path": "out", "scale": 1.5 }),
+            json!({ "path": "out", "scale": 0 }),
+        ] {
+            assert!(RecordingOptions::from_args(&args).is_err(), "accepted {}", args);
+        }
+    }
+
+    #[test]
+    fn huge_size_limit_saturates() {
+        let options = RecordingOptions::from_args(&json!({ "path": "out", "max_size_mb": u64::MAX })).unwrap();
+        assert_eq!(options.max_bytes, u64::MAX);
+    }
+
+    #[test]
+    fn cli_reads_path_and_flags() {
+        let options = cli(&["out.gif", "--fps", "12", "--duration", "1.5", "--max-size-mb", "7", "--scale", "0.25"]).unwrap();
+        assert_eq!(options.path, PathBuf::from("out.gif"));
+        assert_eq!(options.format, RecordingFormat::Gif);
+        assert_eq!(options.fps, 12);
+        assert_eq!(options.max_duration, Duration::from_millis(1500));
+        assert_eq!(options.max_bytes, 7 * 1024 * 1024);
+        assert_eq!(options.scale, 0.25);
+
+        let options = cli(&["--format", "frames", "out.gif"]).unwrap();
+        assert_eq!(options.format, RecordingFormat::Frames);
+    }
+
+    #[test]
+    fn cli_rejects_bad_input() {
+        for args in [
+            &[][..],
+            &["out", "extra"],
+            &["out", "--fps"],
+            &["out", "--fps", "ten"],
+            &["out", "--fps", "4294967296"],
+            &["out", "--duration", "inf"],
+            &["out", "--duration", "-2"],
+            &["out", "--format", "avi"],
+            &["out", "--verbose"],
+        ] {
+            assert!(cli(args).is_err(), "accepted {:?}", args);
+        }
+    }
+
+    #[test]
+    fn gif_delays_follow_capture_timestamps() {
+        assert_eq!(gif_delay_cs(0, 100), 10);
+        // Frames dropped in between stretch the delay to the real gap.
+        assert_eq!(gif_delay_cs(100, 400), 30);
+        // Too-short gaps are raised to what viewers honour.
+        assert_eq!(gif_delay_cs(400, 404), 2);
+    }
+
+    #[test]
+    fn gif_delays_do_not_drift() {
+        // Jittery 30 fps capture: per-frame rounding would lose up to 5 ms a frame.
+        let timestamps: Vec<u64> = (0..300).map(|i| i * 1000 / 30 + i % 3).collect();
+        let total: u64 = timestamps.windows(2).map(|pair| gif_delay_cs(pair[0], pair[1]) as u64).sum();
+        assert_eq!(total, (timestamps[299] + 5) / 10);
+    }
+}
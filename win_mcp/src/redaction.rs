@@ -0,0 +1,238 @@
+use anyhow::{Result, Context};
+use serde::{Serialize, Deserialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use crate::inspector::UiInspector;
+use crate::window_manager::{WindowBackend, WindowState};
+
+/// Environment variable naming a JSON file with the startup `RedactionRules`.
+pub const CONFIG_ENV: &str = "WIN_MCP_REDACTION";
+/// Time the password field search may take before the image is withheld.
+const PASSWORD_SEARCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What to black out of every image before it is encoded.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RedactionRules {
+    /// UI Automation elements with IsPassword set.
+    pub password_fields: bool,
+    /// Case-insensitive substrings of window titles.
+    pub window_titles: Vec<String>,
+    /// Process names such as "KeePass.exe"; the ".exe" suffix is optional.
+    pub processes: Vec<String>,
+    /// Fixed screen rectangles, [left, top, right, bottom].
+    pub rects: Vec<[i32; 4]>,
+}
+
+impl Default for RedactionRules {
+    fn default() -> Self {
+        RedactionRules { password_fields: true, window_titles: Vec::new(), processes: Vec::new(), rects: Vec::new() }
+    }
+}
+
+impl RedactionRules {
+    /// Rules from the file named by `WIN_MCP_REDACTION`, or the defaults when it is unset.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var_os(CONFIG_ENV) {
+            Some(path) => Self::load(Path::new(&path)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid redaction rules in {}", path.display()))
+    }
+
+    /// Adds `other`'s windows, processes and rectangles. Rules can only be widened this way;
+    /// password redaction stays as configured at startup.
+    pub fn extend(&mut self, other: RedactionRules) {
+        self.window_titles.extend(other.window_titles);
+        self.processes.extend(other.processes);
+        self.rects.extend(other.rects);
+    }
+}
+
+/// One blacked-out area, reported back so the caller knows what it cannot see.
+#[derive(Serialize, Clone, Debug)]
+pub struct Redaction {
+    /// "password_field", "window" or "rect".
+    pub reason: &'static str,
+    pub rect: [i32; 4], // [left, top, right, bottom]
+    /// The window title or element name that matched; never the redacted content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched: Option<String>,
+}
+
+/// The current rules plus what it takes to look them up, shared by every tool that produces
+/// images and by recordings, so rules widened mid-recording apply to the next frame.
+#[derive(Clone)]
+pub struct Redactor {
+    rules: Arc<Mutex<RedactionRules>>,
+    inspector: Arc<UiInspector>,
+    windows: Arc<dyn WindowBackend>,
+}
+
+impl Redactor {
+    pub fn new(rules: RedactionRules, inspector: Arc<UiInspector>, windows: Arc<dyn WindowBackend>) -> Self {
+        Redactor { rules: Arc::new(Mutex::new(rules)), inspector, windows }
+    }
+
+    pub fn rules(&self) -> MutexGuard<'_, RedactionRules> {
+        self.rules.lock().unwrap()
+    }
+
+    /// Redactions for an image of `width` x `height` screen pixels starting at `origin`.
+    /// Makes blocking UI Automation calls, so run it off the async runtime.
+    pub fn locate(&self, origin: [u32; 2], width: u32, height: u32) -> Result<Vec<Redaction>> {
+        let rules = self.rules().clone();
+        let edge = |start: u32, length: u32| i32::try_from(start as u64 + length as u64).unwrap_or(i32::MAX);
+        let area = [edge(origin[0], 0), edge(origin[1], 0), edge(origin[0], width), edge(origin[1], height)];
+        locate(&rules, &self.inspector, self.windows.as_ref(), area)
+    }
+}
+
+/// Screen areas the rules cover right now within `area`, [left, top, right, bottom].
+/// A failed lookup is an error, so callers never send an image they could not check.
+pub fn locate(rules: &RedactionRules, inspector: &UiInspector, windows: &dyn WindowBackend, area: [i32; 4]) -> Result<Vec<Redaction>> {
+    let mut found: Vec<Redaction> = rules.rects.iter().map(|&rect| Redaction { reason: "rect", rect, matched: None }).collect();
+
+    let by_window = !rules.window_titles.is_empty() || !rules.processes.is_empty();
+    if by_window || rules.password_fields {
+        let titles: Vec<String> = rules.window_titles.iter().map(|t| t.to_lowercase()).collect();
+        let processes: Vec<String> = rules.processes.iter().map(|p| process_key(p)).collect();
+        let mut searched = Vec::new();
+        for window in windows.list_windows().context("Failed to list windows for redaction")? {
+            if matches!(window.state, WindowState::Minimized) || !intersects(window.bounds, area) {
+                continue;
+            }
+            searched.push(window.handle);
+            let title = window.title.to_lowercase();
+            let by_title = titles.iter().any(|t| title.contains(t.as_str()));
+            let by_process = processes.contains(&process_key(&window.process_name));
+            if by_title || by_process {
+                found.push(Redaction { reason: "window", rect: window.bounds, matched: Some(window.title) });
+            }
+        }
+
+        // Only windows that show up in the image are searched, which keeps the cross-process walk small.
+        if rules.password_fields {
+            let fields = inspector.password_fields(&searched, PASSWORD_SEARCH_TIMEOUT)
+                .context("Failed to search password fields for redaction")?;
+            for field in fields {
+                found.push(Redaction { reason: "password_field", rect: field.rect, matched: Some(field.name).filter(|n| !n.is_empty()) });
+            }
+        }
+    }
+
+    found.retain(|redaction| intersects(redaction.rect, area));
+    Ok(found)
+}
+
+/// Blacks out every redaction on a tightly packed BGRA image of the screen whose
+/// top-left pixel is at screen position `origin`.
+pub fn apply(image: &mut [u8], width: u32, height: u32, origin: [u32; 2], redactions: &[Redaction]) {
+    for redaction in redactions {
+        let [left, top, right, bottom] = redaction.rect;
+        // Widened so rules near the ends of the i32 range cannot overflow.
+        let clip = |value: i32, origin: u32, limit: u32| (value as i64 - origin as i64).clamp(0, limit as i64) as usize;
+        let (x0, x1) = (clip(left, origin[0], width), clip(right, origin[0], width));
+        let (y0, y1) = (clip(top, origin[1], height), clip(bottom, origin[1], height));
+        if x1 <= x0 || y1 <= y0 {
+            continue;
+        }
+        for y in y0..y1 {
+            let start = (y * width as usize + x0) * 4;
+            for pixel in image[start..start + (x1 - x0) * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[0, 0, 0, 0xFF]);
+            }
+        }
+    }
+}
+
+fn intersects(a: [i32; 4], b: [i32; 4]) -> bool {
+    a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+fn process_key(name: &str) -> String {
+    let name = name.to_lowercase();
+    name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: [u8; 4] = [0xFF; 4];
+    const BLACK: [u8; 4] = [0, 0, 0, 0xFF];
+
+    fn white(width: u32, height: u32) -> Vec<u8> {
+        WHITE.repeat((width * height) as usize)
+    }
+
+    fn rect(rect: [i32; 4]) -> Redaction {
+        Redaction { reason: "rect", rect, matched: None }
+    }
+
+    /// Coordinates of the blacked-out pixels, row by row.
+    fn black(image: &[u8], width: u32) -> Vec<(u32, u32)> {
+        image.chunks_exact(4)
+            .enumerate()
+            .filter(|(_, pixel)| *pixel == BLACK)
+            .map(|(i, _)| (i as u32 % width, i as u32 / width))
+            .collect()
+    }
+
+    #[test]
+    fn blacks_out_the_rect() {
+        let mut image = white(4, 3);
+        apply(&mut image, 4, 3, [0, 0], &[rect([1, 1, 3, 2])]);
+        assert_eq!(black(&image, 4), [(1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn clips_rects_partly_off_the_image() {
+        let mut image = white(4, 3);
+        apply(&mut image, 4, 3, [0, 0], &[rect([-5, 2, 2, 10])]);
+        assert_eq!(black(&image, 4), [(0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn ignores_rects_outside_the_image() {
+        let mut image = white(4, 3);
+        apply(&mut image, 4, 3, [0, 0], &[rect([4, 0, 8, 3]), rect([0, -3, 4, 0]), rect([i32::MIN, i32::MIN, -1, -1])]);
+        assert!(black(&image, 4).is_empty());
+    }
+
+    #[test]
+    fn offsets_by_the_image_origin() {
+        // The image shows screen pixels (100, 50) to (104, 53).
+        let mut image = white(4, 3);
+        apply(&mut image, 4, 3, [100, 50], &[rect([103, 51, 200, 52]), rect([0, 0, 100, 50])]);
+        assert_eq!(black(&image, 4), [(3, 1)]);
+    }
+
+    #[test]
+    fn ignores_inverted_rects() {
+        let mut image = white(4, 3);
+        apply(&mut image, 4, 3, [0, 0], &[rect([3, 0, 1, 3]), rect([0, 2, 4, 1]), rect([i32::MAX, i32::MAX, i32::MIN, i32::MIN])]);
+        assert!(black(&image, 4).is_empty());
+    }
+
+    #[test]
+    fn process_key_ignores_case_and_exe_suffix() {
+        assert_eq!(process_key("KeePass.exe"), "keepass");
+        assert_eq!(process_key("KEEPASS.EXE"), "keepass");
+        assert_eq!(process_key("keepass"), "keepass");
+        assert_eq!(process_key("1Password"), "1password");
+        assert_eq!(process_key("setup.exe.bak"), "setup.exe.bak");
+    }
+
+    #[test]
+    fn intersects_needs_shared_area() {
+        assert!(intersects([0, 0, 10, 10], [5, 5, 15, 15]));
+        assert!(!intersects([0, 0, 10, 10], [10, 0, 20, 10]));
+        assert!(!intersects([0, 0, 10, 10], [0, 20, 10, 30]));
+    }
+}
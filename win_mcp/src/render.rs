@@ -0,0 +1,343 @@
+use crate::inspector::UiElement;
+use std::fmt::Write;
+
+/// Text renderings of a UI tree, selectable per tool call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Outline,
+    Markdown,
+    Yaml,
+    Xml,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "outline" => Some(Self::Outline),
+            "markdown" | "md" => Some(Self::Markdown),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "xml" => Some(Self::Xml),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Outline => "outline",
+            Self::Markdown => "markdown",
+            Self::Yaml => "yaml",
+            Self::Xml => "xml",
+        }
+    }
+}
+
+pub fn render(tree: &UiElement, format: OutputFormat) -> String {
+    let mut out = String::new();
+    match format {
+        OutputFormat::Json => out = serde_json::to_string(tree).unwrap_or_default(),
+        OutputFormat::Outline => write_outline(&mut out, tree, 0),
+        OutputFormat::Markdown => write_markdown(&mut out, tree, 0),
+        OutputFormat::Yaml => write_yaml(&mut out, tree, 0),
+        OutputFormat::Xml => {
+            out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<hierarchy>\n");
+            write_xml(&mut out, tree, 1);
+            out.push_str("</hierarchy>\n");
+        }
+    }
+    out
+}
+
+/// Rough token estimate for LLM context budgeting (~4 characters per token).
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Converts `[left, top, right, bottom]` into `(x, y, width, height)`.
+fn bounds(el: &UiElement) -> (i32, i32, i32, i32) {
+    let [left, top, right, bottom] = el.rect;
+    (left, top, right - left, bottom - top)
+}
+
+/// `[button] "Save" #saveBtn @(10,20,80,24)`
+fn write_outline(out: &mut String, el: &UiElement, depth: usize) {
+    let (x, y, w, h) = bounds(el);
+    let _ = write!(out, "{}[{}]", "  ".repeat(depth), type_label(el));
+    if !el.name.is_empty() {
+        let _ = write!(out, " {:?}", el.name);
+    }
+    if !el.automation_id.is_empty() {
+        let _ = write!(out, " #{}", el.automation_id);
+    }
+    let _ = write!(out, " @({},{},{},{})", x, y, w, h);
+    for (key, value, is_text) in extras(el) {
+        let _ = write!(out, " {}={}", key, display_value(&value, is_text));
+    }
+    out.push('\n');
+
+    for child in &el.children {
+        write_outline(out, child, depth + 1);
+    }
+    if let Some(more) = el.more_children {
+        let _ = writeln!(out, "{}+{} more", "  ".repeat(depth + 1), more);
+    }
+}
+
+fn write_markdown(out: &mut String, el: &UiElement, depth: usize) {
+    let (x, y, w, h) = bounds(el);
+    let _ = write!(out, "{}- **{}**", "  ".repeat(depth), type_label(el));
+    if !el.name.is_empty() {
+        let _ = write!(out, " \"{}\"", el.name.replace('\n', " "));
+    }
+    if !el.automation_id.is_empty() {
+        let _ = write!(out, " `#{}`", el.automation_id);
+    }
+    let _ = write!(out, " @({},{},{},{})", x, y, w, h);
+    for (key, value, is_text) in extras(el) {
+        let _ = write!(out, " {}={}", key, display_value(&value, is_text));
+    }
+    out.push('\n');
+
+    for child in &el.children {
+        write_markdown(out, child, depth + 1);
+    }
+    if let Some(more) = el.more_children {
+        let _ = writeln!(out, "{}- *+{} more*", "  ".repeat(depth + 1), more);
+    }
+}
+
+fn write_yaml(out: &mut String, el: &UiElement, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let (x, y, w, h) = bounds(el);
+    let _ = writeln!(out, "{}- type: {}", indent, yaml_string(type_label(el)));
+    if !el.name.is_empty() {
+        let _ = writeln!(out, "{}  name: {}", indent, yaml_string(&el.name));
+    }
+    if !el.automation_id.is_empty() {
+        let _ = writeln!(out, "{}  id: {}", indent, yaml_string(&el.automation_id));
+    }
+    let _ = writeln!(out, "{}  rect: [{}, {}, {}, {}]", indent, x, y, w, h);
+    for (key, value, is_text) in extras(el) {
+        let _ = writeln!(out, "{}  {}: {}", indent, key, display_value(&value, is_text));
+    }
+    if let Some(more) = el.more_children {
+        let _ = writeln!(out, "{}  more_children: {}", indent, more);
+    }
+
+    if !el.children.is_empty() {
+        let _ = writeln!(out, "{}  children:", indent);
+        for child in &el.children {
+            write_yaml(out, child, depth + 2);
+        }
+    }
+}
+
+/// Appium-style page source: one element per node, tagged by control type.
+fn write_xml(out: &mut String, el: &UiElement, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let tag = xml_tag(el);
+    let (x, y, w, h) = bounds(el);
+
+    let _ = write!(out, "{}<{}", indent, tag);
+    if !el.name.is_empty() {
+        let _ = write!(out, " Name=\"{}\"", xml_escape(&el.name));
+    }
+    if !el.automation_id.is_empty() {
+        let _ = write!(out, " AutomationId=\"{}\"", xml_escape(&el.automation_id));
+    }
+    if !el.control_type.is_empty() {
+        let _ = write!(out, " LocalizedControlType=\"{}\"", xml_escape(&el.control_type));
+    }
+    let _ = write!(out, " x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"", x, y, w, h);
+    for (key, value, _) in extras(el) {
+        let _ = write!(out, " {}=\"{}\"", pascal_case(key), xml_escape(&value));
+    }
+
+    if el.children.is_empty() && el.more_children.is_none() {
+        out.push_str("/>\n");
+        return;
+    }
+    out.push_str(">\n");
+    for child in &el.children {
+        write_xml(out, child, depth + 1);
+    }
+    if let Some(more) = el.more_children {
+        let _ = writeln!(out, "{}  <!-- +{} more -->", indent, more);
+    }
+    let _ = writeln!(out, "{}</{}>", indent, tag);
+}
+
+/// Optional properties that were fetched, as `(snake_case key, value, is_text)`.
+fn extras(el: &UiElement) -> Vec<(&'static str, String, bool)> {
+    let mut out = Vec::new();
+    let flags = [
+        ("is_enabled", el.is_enabled),
+        ("is_offscreen", el.is_offscreen),
+        ("has_keyboard_focus", el.has_keyboard_focus),
+        ("is_keyboard_focusable", el.is_keyboard_focusable),
+    ];
+    for (key, flag) in flags {
+        if let Some(flag) = flag {
+            out.push((key, flag.to_string(), false));
+        }
+    }
+
+    let texts = [
+        ("value", &el.value),
+        ("class_name", &el.class_name),
+        ("framework_id", &el.framework_id),
+        ("help_text", &el.help_text),
+        ("accelerator_key", &el.accelerator_key),
+        ("access_key", &el.access_key),
+    ];
+    for (key, text) in texts {
+        if let Some(text) = text {
+            out.push((key, text.clone(), true));
+        }
+    }
+
+    if let Some(pid) = el.process_id {
+        out.push(("process_id", pid.to_string(), false));
+    }
+    if let Some(handle) = el.native_window_handle {
+        out.push(("native_window_handle", format!("0x{:X}", handle), false));
+    }
+    out
+}
+
+/// Text values are JSON-quoted, which also keeps them on one line and valid YAML.
+fn display_value(value: &str, is_text: bool) -> String {
+    if is_text { yaml_string(value) } else { value.to_string() }
+}
+
+/// "is_enabled" -> "IsEnabled", matching UIA property names in XML output.
+fn pascal_case(key: &str) -> String {
+    key.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase().to_string()).unwrap_or_default();
+            first + chars.as_str()
+        })
+        .collect()
+}
+
+fn type_label(el: &UiElement) -> &str {
+    if el.control_type.is_empty() { "element" } else { &el.control_type }
+}
+
+/// Tag from the language-independent control type ("ListItem"), so it reads the same on every UI language.
+fn xml_tag(el: &UiElement) -> &'static str {
+    if el.type_name.is_empty() { "Element" } else { el.type_name }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+}
+
+/// JSON string syntax is valid YAML double-quoted scalar syntax.
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A window with a disabled button whose text needs escaping, and one child left out.
+    fn tree() -> UiElement {
+        let button = UiElement {
+            name: "Save \"draft\" <now>\nor later".to_string(),
+            control_type: "bouton".to_string(),
+            type_name: "Button",
+            automation_id: "save&close".to_string(),
+            rect: [10, 20, 90, 44],
+            is_enabled: Some(false),
+            value: Some("a\"b".to_string()),
+            ..Default::default()
+        };
+        let unknown = UiElement { rect: [0, 0, 5, 5], ..Default::default() };
+        UiElement {
+            name: "Notes".to_string(),
+            control_type: "fenêtre".to_string(),
+            type_name: "Window",
+            rect: [0, 0, 800, 600],
+            children: vec![button, unknown],
+            more_children: Some(2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn outline() {
+        assert_eq!(render(&tree(), OutputFormat::Outline), concat!(
+            "[fenêtre] \"Notes\" @(0,0,800,600)\n",
+            "  [bouton] \"Save \\\"draft\\\" <now>\\nor later\" #save&close @(10,20,80,24) is_enabled=false value=\"a\\\"b\"\n",
+            "  [element] @(0,0,5,5)\n",
+            "  +2 more\n",
+        ));
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq!(render(&tree(), OutputFormat::Markdown), concat!(
+            "- **fenêtre** \"Notes\" @(0,0,800,600)\n",
+            "  - **bouton** \"Save \"draft\" <now> or later\" `#save&close` @(10,20,80,24) is_enabled=false value=\"a\\\"b\"\n",
+            "  - **element** @(0,0,5,5)\n",
+            "  - *+2 more*\n",
+        ));
+    }
+
+    #[test]
+    fn yaml() {
+        assert_eq!(render(&tree(), OutputFormat::Yaml), concat!(
+            "- type: \"fenêtre\"\n",
+            "  name: \"Notes\"\n",
+            "  rect: [0, 0, 800, 600]\n",
+            "  more_children: 2\n",
+            "  children:\n",
+            "    - type: \"bouton\"\n",
+            "      name: \"Save \\\"draft\\\" <now>\\nor later\"\n",
+            "      id: \"save&close\"\n",
+            "      rect: [10, 20, 80, 24]\n",
+            "      is_enabled: false\n",
+            "      value: \"a\\\"b\"\n",
+            "    - type: \"element\"\n",
+            "      rect: [0, 0, 5, 5]\n",
+        ));
+    }
+
+    #[test]
+    fn xml_tags_by_language_independent_type() {
+        assert_eq!(render(&tree(), OutputFormat::Xml), concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<hierarchy>\n",
+            "  <Window Name=\"Notes\" LocalizedControlType=\"fenêtre\" x=\"0\" y=\"0\" width=\"800\" height=\"600\">\n",
+            "    <Button Name=\"Save &quot;draft&quot; &lt;now&gt;&#10;or later\" AutomationId=\"save&amp;close\" LocalizedControlType=\"bouton\" x=\"10\" y=\"20\" width=\"80\" height=\"24\" IsEnabled=\"false\" Value=\"a&quot;b\"/>\n",
+            "    <Element x=\"0\" y=\"0\" width=\"5\" height=\"5\"/>\n",
+            "    <!-- +2 more -->\n",
+            "  </Window>\n",
+            "</hierarchy>\n",
+        ));
+    }
+
+    #[test]
+    fn parses_format_names() {
+        assert_eq!(OutputFormat::parse("MD"), Some(OutputFormat::Markdown));
+        assert_eq!(OutputFormat::parse("yml"), Some(OutputFormat::Yaml));
+        assert_eq!(OutputFormat::parse("html"), None);
+    }
+
+    #[test]
+    fn estimates_four_characters_per_token() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("éééé"), 1);
+    }
+}
@@ -0,0 +1,138 @@
+use windows::Win32::UI::Accessibility::*;
+use windows::core::VARIANT;
+use anyhow::{Result, Context, anyhow};
+use std::time::Duration;
+use crate::inspector::{Selector, UiInspector};
+
+/// Time for a virtualized list to realize newly exposed items after a scroll step.
+const SETTLE_DELAY: Duration = Duration::from_millis(80);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollDirection {
+    Down,
+    Right,
+}
+
+impl ScrollDirection {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "down" => Some(Self::Down),
+            "right" => Some(Self::Right),
+            _ => None,
+        }
+    }
+}
+
+pub struct ScrollSearch {
+    pub found: Option<IUIAutomationElement>,
+    pub steps: usize,
+    pub reached_end: bool,
+    /// "item_container" when UIA located a virtualized item directly, "scrolling" otherwise.
+    pub method: &'static str,
+}
+
+/// Realizes a virtualized element and scrolls its container until it is visible.
+pub fn scroll_into_view(element: &IUIAutomationElement) -> Result<()> {
+    realize(element)?;
+    unsafe {
+        let pattern = element.GetCurrentPatternAs::<IUIAutomationScrollItemPattern>(UIA_ScrollItemPatternId)
+            .context("Element does not support ScrollItemPattern")?;
+        pattern.ScrollIntoView().context("ScrollIntoView failed")
+    }
+}
+
+/// Finds an element matching `selector` inside `container`, scrolling it page by page
+/// from its current position until the element shows up or the end is reached.
+pub fn find_by_scrolling(
+    inspector: &UiInspector,
+    container: &IUIAutomationElement,
+    selector: &Selector,
+    direction: ScrollDirection,
+    max_steps: usize,
+) -> Result<ScrollSearch> {
+    // A virtualized item's properties are placeholders until it is realized, so realize before matching.
+    if let Some(item) = find_in_item_container(container, selector)
+        && realize(&item).is_ok()
+        && inspector.matches(&item, selector)?
+    {
+        scroll_into_view(&item)?;
+        return Ok(ScrollSearch { found: Some(item), steps: 0, reached_end: false, method: "item_container" });
+    }
+
+    let scroll = scroll_pattern(inspector, container)?;
+    let mut steps = 0;
+    loop {
+        if let Some(element) = inspector.find_in(container, TreeScope_Descendants, selector)?.into_iter().next() {
+            // Off-screen items in non-virtualized lists exist in the tree but have stale rects.
+            let _ = scroll_into_view(&element);
+            return Ok(ScrollSearch { found: Some(element), steps, reached_end: false, method: "scrolling" });
+        }
+        if steps >= max_steps {
+            return Ok(ScrollSearch { found: None, steps, reached_end: false, method: "scrolling" });
+        }
+
+        let before = position(&scroll, direction)?;
+        if before.is_none_or(|percent| percent >= 100.0) {
+            return Ok(ScrollSearch { found: None, steps, reached_end: true, method: "scrolling" });
+        }
+        let (horizontal, vertical) = match direction {
+            ScrollDirection::Down => (ScrollAmount_NoAmount, ScrollAmount_LargeIncrement),
+            ScrollDirection::Right => (ScrollAmount_LargeIncrement, ScrollAmount_NoAmount),
+        };
+        unsafe { scroll.Scroll(horizontal, vertical).context("Scroll failed")? };
+        steps += 1;
+        std::thread::sleep(SETTLE_DELAY);
+
+        // Some controls clamp below 100% at the end; no movement means there is nothing left.
+        if position(&scroll, direction)? == before {
+            return Ok(ScrollSearch { found: None, steps, reached_end: true, method: "scrolling" });
+        }
+    }
+}
+
+/// Realizes `element` if it is a virtualized item; anything else is already real.
+fn realize(element: &IUIAutomationElement) -> Result<()> {
+    unsafe {
+        if let Ok(virtualized) = element.GetCurrentPatternAs::<IUIAutomationVirtualizedItemPattern>(UIA_VirtualizedItemPatternId) {
+            virtualized.Realize().context("Failed to realize virtualized item")?;
+        }
+    }
+    Ok(())
+}
+
+/// ItemContainerPattern can find virtualized items that are not in the tree yet,
+/// but only by a single exact property.
+fn find_in_item_container(container: &IUIAutomationElement, selector: &Selector) -> Option<IUIAutomationElement> {
+    let (property, value) = match (&selector.automation_id, &selector.name) {
+        (Some(id), _) => (UIA_AutomationIdPropertyId, id),
+        (None, Some(name)) => (UIA_NamePropertyId, name),
+        (None, None) => return None,
+    };
+    unsafe {
+        let pattern = container.GetCurrentPatternAs::<IUIAutomationItemContainerPattern>(UIA_ItemContainerPatternId).ok()?;
+        pattern.FindItemByProperty(None::<&IUIAutomationElement>, property, &VARIANT::from(value.as_str())).ok()
+    }
+}
+
+/// ScrollPattern of the container or of its nearest scrollable ancestor.
+fn scroll_pattern(inspector: &UiInspector, container: &IUIAutomationElement) -> Result<IUIAutomationScrollPattern> {
+    let mut current = Some(container.clone());
+    while let Some(candidate) = current {
+        if let Ok(pattern) = unsafe { candidate.GetCurrentPatternAs::<IUIAutomationScrollPattern>(UIA_ScrollPatternId) } {
+            return Ok(pattern);
+        }
+        current = inspector.parent(&candidate);
+    }
+    Err(anyhow!("Container is not scrollable"))
+}
+
+/// Scroll position in percent, or `None` when the container cannot scroll that way.
+fn position(scroll: &IUIAutomationScrollPattern, direction: ScrollDirection) -> Result<Option<f64>> {
+    unsafe {
+        let (scrollable, percent) = match direction {
+            ScrollDirection::Down => (scroll.CurrentVerticallyScrollable()?, scroll.CurrentVerticalScrollPercent()?),
+            ScrollDirection::Right => (scroll.CurrentHorizontallyScrollable()?, scroll.CurrentHorizontalScrollPercent()?),
+        };
+        Ok(scrollable.as_bool().then_some(percent))
+    }
+}
@@ -0,0 +1,180 @@
+use windows::Win32::UI::Accessibility::*;
+use windows::core::BSTR;
+use anyhow::{Result, Context, anyhow};
+use serde::Serialize;
+use crate::inspector::UiInspector;
+
+#[derive(Serialize, Debug)]
+pub struct TableData {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub row_offset: usize,
+    pub total_rows: usize,
+    pub total_columns: usize,
+    pub has_more: bool,
+}
+
+/// An element exposing GridPattern (and usually TablePattern), found on the target or an ancestor.
+pub struct TableTarget {
+    grid: IUIAutomationGridPattern,
+    table: Option<IUIAutomationTablePattern>,
+}
+
+impl TableTarget {
+    pub fn locate(inspector: &UiInspector, element: &IUIAutomationElement) -> Result<Self> {
+        let mut current = Some(element.clone());
+        while let Some(candidate) = current {
+            if let Ok(grid) = unsafe { candidate.GetCurrentPatternAs::<IUIAutomationGridPattern>(UIA_GridPatternId) } {
+                let table = unsafe { candidate.GetCurrentPatternAs::<IUIAutomationTablePattern>(UIA_TablePatternId).ok() };
+                return Ok(Self { grid, table });
+            }
+            current = inspector.parent(&candidate);
+        }
+        Err(anyhow!("Element is not inside a grid or table"))
+    }
+
+    pub fn size(&self) -> Result<(usize, usize)> {
+        unsafe {
+            let rows = self.grid.CurrentRowCount().context("Failed to read row count")?;
+            let columns = self.grid.CurrentColumnCount().context("Failed to read column count")?;
+            Ok((rows.max(0) as usize, columns.max(0) as usize))
+        }
+    }
+
+    /// Column headers from TablePattern, or "Column N" when the control has none.
+    pub fn headers(&self, columns: usize) -> Vec<String> {
+        let names: Vec<String> = self.table.as_ref()
+            .and_then(|table| unsafe { table.GetCurrentColumnHeaders().ok() })
+            .map(|headers| unsafe {
+                let count = headers.Length().unwrap_or(0);
+                (0..count)
+                    .filter_map(|i| headers.GetElement(i).ok())
+                    .map(|header| header.CurrentName().unwrap_or(BSTR::from("")).to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if names.len() == columns {
+            names
+        } else {
+            (1..=columns).map(|i| format!("Column {}", i)).collect()
+        }
+    }
+
+    pub fn cell(&self, row: usize, column: usize) -> Result<IUIAutomationElement> {
+        let (rows, columns) = self.size()?;
+        if row >= rows || column >= columns {
+            return Err(anyhow!("Cell ({}, {}) is outside the {}x{} grid", row, column, rows, columns));
+        }
+        unsafe { self.grid.GetItem(row as i32, column as i32).context("Failed to get cell") }
+    }
+
+    /// Reads up to `row_limit` rows starting at `row_offset`. The size is read once, so cells
+    /// are fetched directly rather than through the bounds-checked `cell`.
+    pub fn read(&self, row_offset: usize, row_limit: usize) -> Result<TableData> {
+        let (total_rows, total_columns) = self.size()?;
+        let start = row_offset.min(total_rows);
+        let end = start.saturating_add(row_limit).min(total_rows);
+
+        let rows = (start..end)
+            .map(|row| {
+                (0..total_columns)
+                    .map(|column| unsafe { self.grid.GetItem(row as i32, column as i32) }.map(|cell| cell_text(&cell)).unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+
+        Ok(TableData {
+            headers: self.headers(total_columns),
+            rows,
+            row_offset: start,
+            total_rows,
+            total_columns,
+            has_more: end < total_rows,
+        })
+    }
+}
+
+/// Editable cells keep their content in ValuePattern; others in the name.
+fn cell_text(cell: &IUIAutomationElement) -> String {
+    unsafe {
+        let value = cell.GetCurrentPatternAs::<IUIAutomationValuePattern>(UIA_ValuePatternId)
+            .and_then(|pattern| pattern.CurrentValue())
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+        if value.is_empty() {
+            cell.CurrentName().unwrap_or(BSTR::from("")).to_string()
+        } else {
+            value
+        }
+    }
+}
+
+/// RFC 4180 CSV with CRLF line ends. Short records are padded with empty fields,
+/// so every line has as many fields as the widest one.
+pub fn to_csv(data: &TableData) -> String {
+    let records = || std::iter::once(&data.headers).chain(data.rows.iter());
+    let width = records().map(Vec::len).max().unwrap_or(0);
+    let mut out = String::new();
+    for record in records() {
+        let mut line: Vec<String> = record.iter().map(|field| csv_field(field)).collect();
+        line.resize(width, String::new());
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(headers: &[&str], rows: &[&[&str]]) -> TableData {
+        let strings = |fields: &[&str]| fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        TableData {
+            headers: strings(headers),
+            rows: rows.iter().map(|row| strings(row)).collect(),
+            row_offset: 0,
+            total_rows: rows.len(),
+            total_columns: headers.len(),
+            has_more: false,
+        }
+    }
+
+    #[test]
+    fn plain_fields_are_not_quoted() {
+        let data = table(&["Name", "Size"], &[&["a.txt", "1 KB"], &["b.txt", ""]]);
+        assert_eq!(to_csv(&data), "Name,Size\r\na.txt,1 KB\r\nb.txt,\r\n");
+    }
+
+    #[test]
+    fn quotes_commas_quotes_and_line_breaks() {
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\rlf"), "\"cr\rlf\"");
+        assert_eq!(csv_field(" spaced "), " spaced ");
+
+        let data = table(&["Note"], &[&["x, \"y\"\nz"]]);
+        assert_eq!(to_csv(&data), "Note\r\n\"x, \"\"y\"\"\nz\"\r\n");
+    }
+
+    #[test]
+    fn pads_ragged_rows_to_the_widest_record() {
+        let data = table(&["A", "B"], &[&["1"], &["1", "2", "3"], &[]]);
+        assert_eq!(to_csv(&data), "A,B,\r\n1,,\r\n1,2,3\r\n,,\r\n");
+    }
+
+    #[test]
+    fn empty_table_has_an_empty_header_line() {
+        assert_eq!(to_csv(&table(&[], &[])), "\r\n");
+    }
+}
@@ -0,0 +1,184 @@
+use windows::Win32::UI::Accessibility::*;
+use windows::Win32::Foundation::BOOL;
+use windows::core::BSTR;
+use anyhow::{Result, Context, anyhow};
+use serde::Serialize;
+use crate::inspector::UiInspector;
+
+/// Which part of the document `TextTarget::read` returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextScope {
+    Document,
+    Visible,
+    Selection,
+}
+
+impl TextScope {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "document" => Some(Self::Document),
+            "visible" => Some(Self::Visible),
+            "selection" => Some(Self::Selection),
+            _ => None,
+        }
+    }
+}
+
+/// Character offsets from the start of the document.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TextReport {
+    pub text: String,
+    /// Offset of `text` within the document (exact when the scope is a single range).
+    pub start: usize,
+    /// Length of the whole scope before paging.
+    pub scope_length: usize,
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caret: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caret_active: Option<bool>,
+    pub selections: Vec<TextSpan>,
+}
+
+/// An element exposing TextPattern, found on the target or one of its ancestors.
+pub struct TextTarget {
+    pub element: IUIAutomationElement,
+    pub pattern: IUIAutomationTextPattern,
+}
+
+impl TextTarget {
+    /// Editors often report focus on a child (caret, line) rather than the document itself.
+    pub fn locate(inspector: &UiInspector, element: &IUIAutomationElement) -> Result<Self> {
+        let mut current = Some(element.clone());
+        while let Some(candidate) = current {
+            if let Ok(pattern) = unsafe { candidate.GetCurrentPatternAs::<IUIAutomationTextPattern>(UIA_TextPatternId) } {
+                return Ok(Self { element: candidate, pattern });
+            }
+            current = inspector.parent(&candidate);
+        }
+        Err(anyhow!("Element does not support TextPattern"))
+    }
+
+    pub fn document(&self) -> Result<IUIAutomationTextRange> {
+        unsafe { self.pattern.DocumentRange().context("Failed to get document range") }
+    }
+
+    /// Offset of `range`'s start (or end) from the start of the document.
+    pub fn offset_of(&self, range: &IUIAutomationTextRange, endpoint: TextPatternRangeEndpoint) -> Result<usize> {
+        unsafe {
+            let prefix = self.document()?;
+            prefix.MoveEndpointByRange(TextPatternRangeEndpoint_End, range, endpoint)?;
+            Ok(prefix.GetText(-1)?.to_string().chars().count())
+        }
+    }
+
+    pub fn span_of(&self, range: &IUIAutomationTextRange) -> Result<TextSpan> {
+        Ok(TextSpan {
+            start: self.offset_of(range, TextPatternRangeEndpoint_Start)?,
+            end: self.offset_of(range, TextPatternRangeEndpoint_End)?,
+        })
+    }
+
+    pub fn selections(&self) -> Result<Vec<IUIAutomationTextRange>> {
+        unsafe { collect_ranges(&self.pattern.GetSelection().context("Failed to get selection")?) }
+    }
+
+    /// Caret range via TextPattern2, with whether the caret is currently active.
+    pub fn caret(&self) -> Option<(IUIAutomationTextRange, bool)> {
+        unsafe {
+            let pattern2 = self.element.GetCurrentPatternAs::<IUIAutomationTextPattern2>(UIA_TextPattern2Id).ok()?;
+            let mut active = BOOL::default();
+            let range = pattern2.GetCaretRange(&mut active).ok()?;
+            Some((range, active.as_bool()))
+        }
+    }
+
+    pub fn caret_offset(&self) -> Option<usize> {
+        let (range, _) = self.caret()?;
+        self.offset_of(&range, TextPatternRangeEndpoint_Start).ok()
+    }
+
+    /// Range covering `[start, end)` character offsets of the document.
+    pub fn range_at(&self, start: usize, end: usize) -> Result<IUIAutomationTextRange> {
+        if start > end {
+            return Err(anyhow!("Range start {} is after its end {}", start, end));
+        }
+        let to_count = |offset: usize| i32::try_from(offset).map_err(|_| anyhow!("Offset {} is too large", offset));
+        unsafe {
+            let range = self.document()?;
+            range.MoveEndpointByRange(TextPatternRangeEndpoint_End, &range, TextPatternRangeEndpoint_Start)?;
+            range.MoveEndpointByUnit(TextPatternRangeEndpoint_End, TextUnit_Character, to_count(end)?)?;
+            range.MoveEndpointByUnit(TextPatternRangeEndpoint_Start, TextUnit_Character, to_count(start)?)?;
+            Ok(range)
+        }
+    }
+
+    /// The `occurrence`-th (1-based) match of `needle`, searching forward from the document start.
+    pub fn find(&self, needle: &str, occurrence: usize, ignore_case: bool) -> Result<Option<IUIAutomationTextRange>> {
+        let needle = BSTR::from(needle);
+        unsafe {
+            let remaining = self.document()?;
+            let mut found = None;
+            for _ in 0..occurrence.max(1) {
+                let Ok(hit) = remaining.FindText(&needle, false, ignore_case) else {
+                    return Ok(None);
+                };
+                remaining.MoveEndpointByRange(TextPatternRangeEndpoint_Start, &hit, TextPatternRangeEndpoint_End)?;
+                found = Some(hit);
+            }
+            Ok(found)
+        }
+    }
+
+    /// Selects `range`; a degenerate range places the caret instead.
+    pub fn select(&self, range: &IUIAutomationTextRange) -> Result<()> {
+        unsafe { range.Select().context("The control refused the selection") }
+    }
+
+    /// Reads `scope`, then returns at most `limit` characters starting `offset` characters into it.
+    pub fn read(&self, scope: TextScope, offset: usize, limit: usize) -> Result<TextReport> {
+        let selection_ranges = self.selections().unwrap_or_default();
+        let ranges = match scope {
+            TextScope::Document => vec![self.document()?],
+            TextScope::Visible => unsafe { collect_ranges(&self.pattern.GetVisibleRanges().context("Failed to get visible ranges")?)? },
+            TextScope::Selection => selection_ranges.clone(),
+        };
+
+        let mut parts = Vec::with_capacity(ranges.len());
+        for range in &ranges {
+            parts.push(unsafe { range.GetText(-1)? }.to_string());
+        }
+        let full: Vec<char> = parts.join("\n").chars().collect();
+        let scope_start = match ranges.first() {
+            Some(first) => self.offset_of(first, TextPatternRangeEndpoint_Start)?,
+            None => 0,
+        };
+
+        let page_start = offset.min(full.len());
+        let page_end = page_start.saturating_add(limit).min(full.len());
+        let caret_active = self.caret().map(|(_, active)| active);
+
+        Ok(TextReport {
+            text: full[page_start..page_end].iter().collect(),
+            start: scope_start + page_start,
+            scope_length: full.len(),
+            has_more: page_end < full.len(),
+            caret: self.caret_offset(),
+            caret_active,
+            selections: selection_ranges.iter().filter_map(|range| self.span_of(range).ok()).collect(),
+        })
+    }
+}
+
+fn collect_ranges(array: &IUIAutomationTextRangeArray) -> Result<Vec<IUIAutomationTextRange>> {
+    unsafe {
+        let count = array.Length()?;
+        (0..count).map(|i| array.GetElement(i).map_err(Into::into)).collect()
+    }
+}
@@ -1,9 +1,22 @@
+pub mod ocr;
+pub mod marks;
+pub mod template;
+pub mod cursor;
+pub mod fingerprint;
+pub mod pixels;
+pub mod grid;
+pub mod region;
+
 use anyhow::{Result, Context, anyhow};
 use windows::Win32::Graphics::Direct3D11::*;
 use windows::Win32::Graphics::Dxgi::*;
 use windows::Win32::Graphics::Dxgi::Common::*;
 use windows::Win32::Graphics::Direct3D::*;
+use windows::Win32::Foundation::RECT;
 use windows::core::Interface;
+use serde::Serialize;
+use cursor::{PointerShape, ShapeKind};
+use region::Region;
 
 pub struct DesktopCapture {
     device: ID3D11Device,
@@ -11,6 +24,72 @@ pub struct DesktopCapture {
     duplication: IDXGIOutputDuplication,
     staging_texture: ID3D11Texture2D,
     desc: DXGI_OUTDUPL_DESC,
+    /// CPU copy of the desktop, BGRA and tightly packed; empty until the first capture.
+    frame: Vec<u8>,
+    /// Changes not yet reported through `take_changes`.
+    pending: FrameChanges,
+    pointer_visible: bool,
+    /// Top-left corner of the pointer shape on the desktop.
+    pointer_origin: [i32; 2],
+    pointer_shape: Option<PointerShape>,
+}
+
+const FIRST_FRAME_TIMEOUT_MS: u32 = 500;
+const FRAME_TIMEOUT_MS: u32 = 50;
+/// Beyond this many dirty rects the pending report collapses them into their bounding box,
+/// and beyond this many move rects it reports their destinations as dirty instead.
+const MAX_PENDING_RECTS: usize = 256;
+
+/// A screen region that was moved (scrolled or dragged) from `source` to `destination`.
+#[derive(Serialize, Clone, Debug)]
+pub struct MoveRect {
+    pub source: [i32; 2], // top-left of the region before the move
+    pub destination: [i32; 4], // [left, top, right, bottom]
+}
+
+/// Where the mouse pointer is, as last reported by the duplication API.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct PointerState {
+    pub visible: bool,
+    /// The pointer's hotspot (click point) in screen pixels.
+    pub position: [i32; 2],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<ShapeKind>,
+}
+
+/// Screen regions that changed between captures, from DXGI frame metadata.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct FrameChanges {
+    /// The whole frame was read, as on the first capture.
+    pub full: bool,
+    pub accumulated_frames: u32,
+    pub move_rects: Vec<MoveRect>,
+    pub dirty_rects: Vec<[i32; 4]>, // [left, top, right, bottom]
+}
+
+impl FrameChanges {
+    /// Whether any reported change overlaps `region`. Pointer-only updates never do.
+    pub fn touches(&self, region: Region) -> bool {
+        let area = [region.x as i32, region.y as i32, (region.x + region.width) as i32, (region.y + region.height) as i32];
+        let overlaps = |r: &[i32; 4]| r[0] < area[2] && area[0] < r[2] && r[1] < area[3] && area[1] < r[3];
+        self.full || self.dirty_rects.iter().any(overlaps) || self.move_rects.iter().any(|m| overlaps(&m.destination))
+    }
+
+    fn merge(&mut self, other: &FrameChanges) {
+        self.full |= other.full;
+        self.accumulated_frames += other.accumulated_frames;
+        self.move_rects.extend(other.move_rects.iter().cloned());
+        self.dirty_rects.extend(other.dirty_rects.iter().copied());
+        if self.move_rects.len() > MAX_PENDING_RECTS {
+            self.dirty_rects.extend(self.move_rects.drain(..).map(|m| m.destination));
+        }
+        if self.dirty_rects.len() > MAX_PENDING_RECTS {
+            let bounds = self.dirty_rects.iter().fold([i32::MAX, i32::MAX, i32::MIN, i32::MIN], |acc, r| {
+                [acc[0].min(r[0]), acc[1].min(r[1]), acc[2].max(r[2]), acc[3].max(r[3])]
+            });
+            self.dirty_rects = vec![bounds];
+        }
+    }
 }
 
 impl DesktopCapture {
@@ -67,63 +146,363 @@ impl DesktopCapture {
                 duplication,
                 staging_texture,
                 desc,
+                frame: Vec::new(),
+                pending: FrameChanges::default(),
+                pointer_visible: false,
+                pointer_origin: [0, 0],
+                pointer_shape: None,
             })
         }
     }
 
-    pub fn capture_frame(&mut self) -> Result<Vec<u8>> {
+    /// Brings the cached frame up to date with the screen and returns what changed.
+    ///
+    /// Only the first frame is read back in full; after that move rects are replayed
+    /// on the CPU copy and just the dirty rects are copied from the GPU.
+    pub fn refresh(&mut self) -> Result<FrameChanges> {
+        let first = self.frame.is_empty();
         unsafe {
             let mut frame_resource: Option<IDXGIResource> = None;
             let mut frame_info = DXGI_OUTDUPL_FRAME_INFO::default();
-            
-            // Acquire next frame (timeout 100ms)
-            match self.duplication.AcquireNextFrame(100, &mut frame_info, &mut frame_resource) {
+
+            let timeout = if first { FIRST_FRAME_TIMEOUT_MS } else { FRAME_TIMEOUT_MS };
+            match self.duplication.AcquireNextFrame(timeout, &mut frame_info, &mut frame_resource) {
                 Ok(_) => (),
+                Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT && !first => return Ok(FrameChanges::default()),
                 Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT => {
                     return Err(anyhow!("Capture timeout (no change)"));
                 }
                 Err(e) => return Err(e.into()),
             }
 
-            // Optimization: Only process if LastPresentTime is non-zero
+            // Pointer data belongs to the acquired frame, so read it before releasing;
+            // a stale pointer is no reason to fail the capture.
+            let _ = self.update_pointer(&frame_info);
+
+            // A zero LastPresentTime means only the pointer moved; the image is unchanged.
             if frame_info.LastPresentTime == 0 {
                 self.duplication.ReleaseFrame().ok();
-                return Err(anyhow!("Frame hasn't changed"));
+                if first {
+                    return Err(anyhow!("Frame hasn't changed"));
+                }
+                return Ok(FrameChanges { accumulated_frames: frame_info.AccumulatedFrames, ..FrameChanges::default() });
             }
-            
-            let frame_resource = frame_resource.unwrap();
+
+            let copied = self.copy_changes(&frame_info, frame_resource, first);
+            let released = self.duplication.ReleaseFrame().context("Failed to release frame");
+            // Later frames report only their own changes, so a cache that missed these stays
+            // wrong for good; drop it and the next refresh reads the whole frame again.
+            let changes = copied
+                .and_then(|changes| {
+                    released?;
+                    self.read_back(&changes)?;
+                    Ok(changes)
+                })
+                .inspect_err(|_| self.frame.clear())?;
+            self.pending.merge(&changes);
+            Ok(changes)
+        }
+    }
+
+    /// Refreshes the cache and returns a copy of the whole frame (BGRA, tightly packed).
+    pub fn capture_frame(&mut self) -> Result<Vec<u8>> {
+        self.refresh()?;
+        Ok(self.frame.clone())
+    }
+
+    /// The cached frame as of the last refresh (BGRA, tightly packed); empty before the first.
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    /// Changes accumulated since the previous call, across every refresh in between.
+    pub fn take_changes(&mut self) -> FrameChanges {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn pointer(&self) -> PointerState {
+        let hotspot = self.pointer_shape.as_ref().map_or([0, 0], |shape| shape.hotspot);
+        PointerState {
+            visible: self.pointer_visible,
+            position: [self.pointer_origin[0] + hotspot[0], self.pointer_origin[1] + hotspot[1]],
+            shape: self.pointer_shape.as_ref().map(|shape| shape.kind),
+        }
+    }
+
+    /// Draws the pointer onto a frame returned by `capture_frame`; Desktop Duplication leaves it out.
+    pub fn composite_pointer(&self, frame: &mut [u8]) {
+        if let (true, Some(shape)) = (self.pointer_visible, &self.pointer_shape) {
+            let (width, height) = self.get_dimensions();
+            cursor::composite(frame, width, height, shape, self.pointer_origin);
+        }
+    }
+
+    fn update_pointer(&mut self, frame_info: &DXGI_OUTDUPL_FRAME_INFO) -> Result<()> {
+        // Zero means neither position nor shape changed since the previous frame.
+        if frame_info.LastMouseUpdateTime == 0 {
+            return Ok(());
+        }
+        self.pointer_visible = frame_info.PointerPosition.Visible.as_bool();
+        self.pointer_origin = [frame_info.PointerPosition.Position.x, frame_info.PointerPosition.Position.y];
+
+        if frame_info.PointerShapeBufferSize > 0 {
+            let mut data = vec![0u8; frame_info.PointerShapeBufferSize as usize];
+            let mut required = 0u32;
+            let mut info = DXGI_OUTDUPL_POINTER_SHAPE_INFO::default();
+            unsafe {
+                self.duplication.GetFramePointerShape(data.len() as u32, data.as_mut_ptr() as *mut _, &mut required, &mut info)
+                    .context("Failed to get pointer shape")?;
+            }
+            data.truncate(required as usize);
+            self.pointer_shape = ShapeKind::from_dxgi(info.Type).map(|kind| PointerShape {
+                kind,
+                width: info.Width,
+                height: info.Height,
+                pitch: info.Pitch,
+                hotspot: [info.HotSpot.x, info.HotSpot.y],
+                data,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads the frame's metadata, replays moves on the CPU copy and queues GPU copies
+    /// of the regions that must be read back into the staging texture.
+    fn copy_changes(&mut self, frame_info: &DXGI_OUTDUPL_FRAME_INFO, frame_resource: Option<IDXGIResource>, first: bool) -> Result<FrameChanges> {
+        unsafe {
+            let frame_resource = frame_resource.ok_or_else(|| anyhow!("Frame has no desktop image"))?;
             let texture: ID3D11Texture2D = frame_resource.cast().context("Failed to cast resource to texture")?;
-            
-            // Copy to staging
-            self.context.CopyResource(&self.staging_texture, &texture);
-            
-            self.duplication.ReleaseFrame().context("Failed to release frame")?;
-            
-            // Map and read
+
+            let (move_rects, dirty_rects) = self.frame_metadata(frame_info)?;
+            let changes = FrameChanges {
+                full: first,
+                accumulated_frames: frame_info.AccumulatedFrames,
+                move_rects,
+                dirty_rects,
+            };
+
+            if first {
+                self.context.CopyResource(&self.staging_texture, &texture);
+                return Ok(changes);
+            }
+
+            let (width, height) = self.get_dimensions();
+            for movement in &changes.move_rects {
+                apply_move(&mut self.frame, width as usize, height as usize, movement);
+            }
+            for rect in &changes.dirty_rects {
+                let region = D3D11_BOX {
+                    left: rect[0] as u32,
+                    top: rect[1] as u32,
+                    front: 0,
+                    right: rect[2] as u32,
+                    bottom: rect[3] as u32,
+                    back: 1,
+                };
+                self.context.CopySubresourceRegion(&self.staging_texture, 0, region.left, region.top, 0, &texture, 0, Some(&region));
+            }
+            Ok(changes)
+        }
+    }
+
+    fn frame_metadata(&mut self, frame_info: &DXGI_OUTDUPL_FRAME_INFO) -> Result<(Vec<MoveRect>, Vec<[i32; 4]>)> {
+        if frame_info.TotalMetadataBufferSize == 0 {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let capacity = frame_info.TotalMetadataBufferSize as usize;
+        unsafe {
+            let mut moves = vec![DXGI_OUTDUPL_MOVE_RECT::default(); capacity / size_of::<DXGI_OUTDUPL_MOVE_RECT>() + 1];
+            let mut required = 0u32;
+            self.duplication.GetFrameMoveRects((moves.len() * size_of::<DXGI_OUTDUPL_MOVE_RECT>()) as u32, moves.as_mut_ptr(), &mut required)
+                .context("Failed to get move rects")?;
+            moves.truncate(required as usize / size_of::<DXGI_OUTDUPL_MOVE_RECT>());
+
+            let mut dirty = vec![RECT::default(); capacity / size_of::<RECT>() + 1];
+            self.duplication.GetFrameDirtyRects((dirty.len() * size_of::<RECT>()) as u32, dirty.as_mut_ptr(), &mut required)
+                .context("Failed to get dirty rects")?;
+            dirty.truncate(required as usize / size_of::<RECT>());
+
+            let moves = moves
+                .iter()
+                .map(|m| MoveRect {
+                    source: [m.SourcePoint.x, m.SourcePoint.y],
+                    destination: [m.DestinationRect.left, m.DestinationRect.top, m.DestinationRect.right, m.DestinationRect.bottom],
+                })
+                .collect();
+            let dirty = dirty.iter().map(|r| [r.left, r.top, r.right, r.bottom]).collect();
+            Ok((moves, dirty))
+        }
+    }
+
+    /// Copies the regions queued by `copy_changes` from the staging texture into the cache.
+    fn read_back(&mut self, changes: &FrameChanges) -> Result<()> {
+        let (width, height) = self.get_dimensions();
+        let (width, height) = (width as usize, height as usize);
+        let full = [[0, 0, width as i32, height as i32]];
+        let rects: &[[i32; 4]] = if changes.full { &full } else { &changes.dirty_rects };
+        if rects.is_empty() {
+            return Ok(());
+        }
+        if self.frame.len() != width * height * 4 {
+            self.frame = vec![0; width * height * 4];
+        }
+
+        unsafe {
             let mut mapped_resource = D3D11_MAPPED_SUBRESOURCE::default();
             self.context.Map(&self.staging_texture, 0, D3D11_MAP_READ, 0, Some(&mut mapped_resource))
                 .context("Failed to map staging texture")?;
-            
-            let width = self.desc.ModeDesc.Width as usize;
-            let height = self.desc.ModeDesc.Height as usize;
             let row_pitch = mapped_resource.RowPitch as usize;
-            
-            let mut buffer = Vec::with_capacity(width * height * 4);
             let ptr = mapped_resource.pData as *const u8;
-            
-            for y in 0..height {
-                let row_start = ptr.add(y * row_pitch);
-                let row_slice = std::slice::from_raw_parts(row_start, width * 4);
-                buffer.extend_from_slice(row_slice);
+
+            for rect in rects {
+                let (left, right) = (rect[0].max(0) as usize, (rect[2].max(0) as usize).min(width));
+                let (top, bottom) = (rect[1].max(0) as usize, (rect[3].max(0) as usize).min(height));
+                if left >= right {
+                    continue;
+                }
+                for y in top..bottom {
+                    let row = std::slice::from_raw_parts(ptr.add(y * row_pitch + left * 4), (right - left) * 4);
+                    let start = (y * width + left) * 4;
+                    self.frame[start..start + row.len()].copy_from_slice(row);
+                }
             }
-            
+
             self.context.Unmap(&self.staging_texture, 0);
-            
-            Ok(buffer)
         }
+        Ok(())
     }
 
     pub fn get_dimensions(&self) -> (u32, u32) {
         (self.desc.ModeDesc.Width, self.desc.ModeDesc.Height)
     }
 }
+
+/// Replays a DXGI move on the CPU frame. The source is read before writing, so
+/// overlapping moves (the common scroll case) are safe.
+fn apply_move(frame: &mut [u8], width: usize, height: usize, movement: &MoveRect) {
+    let [left, top, right, bottom] = movement.destination;
+    let [source_x, source_y] = movement.source;
+    let (w, h) = (right - left, bottom - top);
+    let fits = |x: i32, y: i32| x >= 0 && y >= 0 && x + w <= width as i32 && y + h <= height as i32;
+    if w <= 0 || h <= 0 || !fits(left, top) || !fits(source_x, source_y) {
+        return;
+    }
+
+    let row_bytes = w as usize * 4;
+    let mut region = Vec::with_capacity(row_bytes * h as usize);
+    for y in 0..h as usize {
+        let start = ((source_y as usize + y) * width + source_x as usize) * 4;
+        region.extend_from_slice(&frame[start..start + row_bytes]);
+    }
+    for (y, row) in region.chunks_exact(row_bytes).enumerate() {
+        let start = ((top as usize + y) * width + left as usize) * 4;
+        frame[start..start + row_bytes].copy_from_slice(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 4;
+    const HEIGHT: usize = 3;
+
+    /// Frame whose pixels hold their own index in every channel.
+    fn frame() -> Vec<u8> {
+        (0..(WIDTH * HEIGHT) as u8).flat_map(|i| [i; 4]).collect()
+    }
+
+    fn pixels(frame: &[u8]) -> Vec<u8> {
+        frame.chunks_exact(4).map(|p| p[0]).collect()
+    }
+
+    fn movement(source: [i32; 2], destination: [i32; 4]) -> MoveRect {
+        MoveRect { source, destination }
+    }
+
+    #[test]
+    fn apply_move_handles_overlapping_scrolls() {
+        let mut data = frame();
+        // Scroll up one row: rows 1-2 move to rows 0-1.
+        apply_move(&mut data, WIDTH, HEIGHT, &movement([0, 1], [0, 0, 4, 2]));
+        assert_eq!(pixels(&data), [4, 5, 6, 7, 8, 9, 10, 11, 8, 9, 10, 11]);
+
+        let mut data = frame();
+        // Shift the first row right by one pixel.
+        apply_move(&mut data, WIDTH, HEIGHT, &movement([0, 0], [1, 0, 4, 1]));
+        assert_eq!(pixels(&data)[..4], [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn apply_move_ignores_moves_outside_the_frame() {
+        for bad in [
+            movement([0, 0], [2, 0, 6, 1]),  // destination past the right edge
+            movement([-1, 0], [0, 0, 2, 1]), // source left of the frame
+            movement([0, 2], [0, 0, 4, 2]),  // source past the bottom
+            movement([0, 0], [2, 2, 2, 3]),  // empty
+        ] {
+            let mut data = frame();
+            apply_move(&mut data, WIDTH, HEIGHT, &bad);
+            assert_eq!(data, frame(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn touches_reports_overlapping_changes_only() {
+        let region = Region { x: 10, y: 10, width: 10, height: 10 };
+        assert!(!FrameChanges::default().touches(region));
+        assert!(FrameChanges { full: true, ..FrameChanges::default() }.touches(region));
+
+        let dirty = |rect: [i32; 4]| FrameChanges { dirty_rects: vec![rect], ..FrameChanges::default() };
+        assert!(dirty([15, 15, 16, 16]).touches(region));
+        assert!(dirty([0, 0, 11, 11]).touches(region));
+        // Edges are exclusive: rects that only share a border do not touch.
+        assert!(!dirty([0, 0, 10, 30]).touches(region));
+        assert!(!dirty([20, 10, 30, 20]).touches(region));
+
+        let moved = |destination: [i32; 4]| FrameChanges { move_rects: vec![movement([0, 0], destination)], ..FrameChanges::default() };
+        assert!(moved([12, 0, 14, 12]).touches(region));
+        assert!(!moved([30, 30, 40, 40]).touches(region));
+    }
+
+    #[test]
+    fn merge_accumulates_changes() {
+        let mut pending = FrameChanges::default();
+        pending.merge(&FrameChanges { accumulated_frames: 2, dirty_rects: vec![[0, 0, 1, 1]], ..FrameChanges::default() });
+        pending.merge(&FrameChanges {
+            full: true,
+            accumulated_frames: 1,
+            move_rects: vec![movement([0, 0], [0, 1, 1, 2])],
+            dirty_rects: vec![[2, 2, 3, 3]],
+        });
+        assert!(pending.full);
+        assert_eq!(pending.accumulated_frames, 3);
+        assert_eq!(pending.dirty_rects, [[0, 0, 1, 1], [2, 2, 3, 3]]);
+        assert_eq!(pending.move_rects.len(), 1);
+    }
+
+    #[test]
+    fn merge_collapses_too_many_dirty_rects_into_their_bounds() {
+        let mut pending = FrameChanges::default();
+        for i in 0..=MAX_PENDING_RECTS as i32 {
+            pending.merge(&FrameChanges { dirty_rects: vec![[i, 5, i + 1, 6 + i % 3]], ..FrameChanges::default() });
+        }
+        assert_eq!(pending.dirty_rects, [[0, 5, MAX_PENDING_RECTS as i32 + 1, 8]]);
+    }
+
+    #[test]
+    fn merge_reports_too_many_moves_as_dirty_rects() {
+        let mut pending = FrameChanges::default();
+        for i in 0..MAX_PENDING_RECTS as i32 {
+            pending.merge(&FrameChanges { move_rects: vec![movement([0, 0], [i, 0, i + 1, 1])], ..FrameChanges::default() });
+        }
+        assert_eq!(pending.move_rects.len(), MAX_PENDING_RECTS);
+        assert!(pending.dirty_rects.is_empty());
+
+        pending.merge(&FrameChanges { move_rects: vec![movement([0, 0], [0, 10, 5, 20])], ..FrameChanges::default() });
+        assert!(pending.move_rects.is_empty());
+        // The destinations became dirty rects, then collapsed into their bounds.
+        assert_eq!(pending.dirty_rects, [[0, 0, MAX_PENDING_RECTS as i32, 20]]);
+        assert!(pending.touches(Region { x: 2, y: 12, width: 1, height: 1 }));
+    }
+}
@@ -0,0 +1,198 @@
+use serde::Serialize;
+
+/// Pointer shape formats reported by `GetFramePointerShape` (`DXGI_OUTDUPL_POINTER_SHAPE_TYPE`).
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeKind {
+    /// 1 bpp AND mask followed by a 1 bpp XOR mask, each `height / 2` rows.
+    Monochrome,
+    /// 32 bpp BGRA blended with its alpha channel.
+    Color,
+    /// 32 bpp BGR whose alpha byte selects replace (0x00) or XOR (0xFF).
+    MaskedColor,
+}
+
+impl ShapeKind {
+    pub fn from_dxgi(kind: u32) -> Option<Self> {
+        match kind {
+            1 => Some(Self::Monochrome),
+            2 => Some(Self::Color),
+            4 => Some(Self::MaskedColor),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PointerShape {
+    pub kind: ShapeKind,
+    pub width: u32,
+    /// Buffer rows; for monochrome shapes twice the visible height.
+    pub height: u32,
+    pub pitch: u32,
+    /// Offset of the click point from the shape's top-left corner.
+    pub hotspot: [i32; 2],
+    pub data: Vec<u8>,
+}
+
+impl PointerShape {
+    pub fn visible_height(&self) -> u32 {
+        match self.kind {
+            ShapeKind::Monochrome => self.height / 2,
+            ShapeKind::Color | ShapeKind::MaskedColor => self.height,
+        }
+    }
+}
+
+/// Draws `shape` with its top-left corner at `origin` onto a tightly packed BGRA frame,
+/// clipping whatever falls outside it.
+pub fn composite(frame: &mut [u8], width: u32, height: u32, shape: &PointerShape, origin: [i32; 2]) {
+    let (frame_width, frame_height) = (width as i32, height as i32);
+    for row in 0..shape.visible_height() as i32 {
+        let y = origin[1] + row;
+        if y < 0 || y >= frame_height {
+            continue;
+        }
+        for column in 0..shape.width as i32 {
+            let x = origin[0] + column;
+            if x < 0 || x >= frame_width {
+                continue;
+            }
+            let offset = ((y * frame_width + x) * 4) as usize;
+            let pixel = &mut frame[offset..offset + 4];
+            blend(pixel, shape, column as usize, row as usize);
+        }
+    }
+}
+
+fn blend(pixel: &mut [u8], shape: &PointerShape, x: usize, y: usize) {
+    match shape.kind {
+        ShapeKind::Monochrome => {
+            let bit = |row: usize| shape.data[row * shape.pitch as usize + x / 8] & (0x80 >> (x % 8)) != 0;
+            let and = bit(y);
+            let xor = bit(y + shape.visible_height() as usize);
+            for channel in &mut pixel[..3] {
+                let masked = if and { *channel } else { 0 };
+                *channel = if xor { masked ^ 0xFF } else { masked };
+            }
+        }
+        ShapeKind::Color => {
+            let source = &shape.data[y * shape.pitch as usize + x * 4..][..4];
+            let alpha = source[3] as u32;
+            for (channel, &value) in pixel[..3].iter_mut().zip(&source[..3]) {
+                *channel = ((value as u32 * alpha + *channel as u32 * (255 - alpha) + 127) / 255) as u8;
+            }
+        }
+        ShapeKind::MaskedColor => {
+            let source = &shape.data[y * shape.pitch as usize + x * 4..][..4];
+            for (channel, &value) in pixel[..3].iter_mut().zip(&source[..3]) {
+                *channel = if source[3] == 0 { value } else { *channel ^ value };
+            }
+        }
+    }
+    pixel[3] = 0xFF;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u32, height: u32, bgra: [u8; 4]) -> Vec<u8> {
+        bgra.repeat((width * height) as usize)
+    }
+
+    fn pixel(frame: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * width + x) * 4) as usize;
+        frame[offset..offset + 4].try_into().unwrap()
+    }
+
+    /// One row, four pixels: transparent, black, white, inverted.
+    fn monochrome() -> PointerShape {
+        PointerShape {
+            kind: ShapeKind::Monochrome,
+            width: 4,
+            height: 2,
+            pitch: 1,
+            hotspot: [0, 0],
+            data: vec![0b1001_0000, 0b0011_0000],
+        }
+    }
+
+    #[test]
+    fn monochrome_applies_and_then_xor() {
+        let mut target = frame(4, 1, [10, 20, 30, 255]);
+        composite(&mut target, 4, 1, &monochrome(), [0, 0]);
+        assert_eq!(pixel(&target, 4, 0, 0), [10, 20, 30, 255]);
+        assert_eq!(pixel(&target, 4, 1, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&target, 4, 2, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&target, 4, 3, 0), [245, 235, 225, 255]);
+    }
+
+    #[test]
+    fn color_blends_by_alpha() {
+        let shape = PointerShape {
+            kind: ShapeKind::Color,
+            width: 3,
+            height: 1,
+            pitch: 12,
+            hotspot: [0, 0],
+            data: vec![200, 100, 0, 255, 200, 100, 0, 0, 200, 100, 0, 128],
+        };
+        let mut target = frame(3, 1, [0, 0, 200, 255]);
+        composite(&mut target, 3, 1, &shape, [0, 0]);
+        assert_eq!(pixel(&target, 3, 0, 0), [200, 100, 0, 255]);
+        assert_eq!(pixel(&target, 3, 1, 0), [0, 0, 200, 255]);
+        assert_eq!(pixel(&target, 3, 2, 0), [100, 50, 100, 255]);
+    }
+
+    #[test]
+    fn masked_color_replaces_or_xors() {
+        let shape = PointerShape {
+            kind: ShapeKind::MaskedColor,
+            width: 2,
+            height: 1,
+            pitch: 8,
+            hotspot: [0, 0],
+            data: vec![1, 2, 3, 0x00, 0xFF, 0x0F, 0x00, 0xFF],
+        };
+        let mut target = frame(2, 1, [0xF0, 0xF0, 0xF0, 255]);
+        composite(&mut target, 2, 1, &shape, [0, 0]);
+        assert_eq!(pixel(&target, 2, 0, 0), [1, 2, 3, 255]);
+        assert_eq!(pixel(&target, 2, 1, 0), [0x0F, 0xFF, 0xF0, 255]);
+    }
+
+    #[test]
+    fn clips_shape_at_frame_edges() {
+        let shape = PointerShape {
+            kind: ShapeKind::MaskedColor,
+            width: 2,
+            height: 2,
+            pitch: 8,
+            hotspot: [0, 0],
+            data: [9, 9, 9, 0].repeat(4),
+        };
+        let mut target = frame(2, 2, [0, 0, 0, 255]);
+        composite(&mut target, 2, 2, &shape, [-1, 1]);
+        assert_eq!(pixel(&target, 2, 0, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&target, 2, 1, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&target, 2, 0, 1), [9, 9, 9, 255]);
+        assert_eq!(pixel(&target, 2, 1, 1), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn monochrome_reads_padded_rows() {
+        // Two rows of one visible pixel each, padded to a 2-byte pitch.
+        let shape = PointerShape {
+            kind: ShapeKind::Monochrome,
+            width: 1,
+            height: 4,
+            pitch: 2,
+            hotspot: [0, 0],
+            data: vec![0x00, 0xAA, 0x80, 0xAA, 0x00, 0xAA, 0x80, 0xAA],
+        };
+        let mut target = frame(1, 2, [50, 50, 50, 255]);
+        composite(&mut target, 1, 2, &shape, [0, 0]);
+        assert_eq!(pixel(&target, 1, 0, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&target, 1, 0, 1), [205, 205, 205, 255]);
+    }
+}
@@ -0,0 +1,158 @@
+use serde::Serialize;
+use super::region::Region;
+
+/// Cells per side of the grid a region is reduced to.
+const GRID: u32 = 32;
+/// Mean luma difference a cell must exceed to count as changed. Keeps a blinking
+/// caret or anti-aliasing jitter from registering as a change.
+const CELL_TOLERANCE: u8 = 6;
+
+/// A coarse perceptual hash of a screen region: the mean luma of each grid cell.
+#[derive(Clone, Debug)]
+pub struct Fingerprint {
+    region: Region,
+    columns: u32,
+    rows: u32,
+    cells: Vec<u8>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct Difference {
+    /// Share of grid cells that changed, 0.0 to 1.0.
+    pub changed_fraction: f32,
+    /// Bounding box of the changed cells in screen pixels, [left, top, right, bottom].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_rect: Option<[i32; 4]>,
+}
+
+impl Fingerprint {
+    /// Hashes `region` of a tightly packed BGRA frame `width` pixels wide.
+    pub fn of(frame: &[u8], width: u32, region: Region) -> Self {
+        let columns = GRID.min(region.width).max(1);
+        let rows = GRID.min(region.height).max(1);
+        let mut cells = Vec::with_capacity((columns * rows) as usize);
+
+        for row in 0..rows {
+            let (top, bottom) = span(region.y, region.height, rows, row);
+            for column in 0..columns {
+                let (left, right) = span(region.x, region.width, columns, column);
+                let mut sum = 0u64;
+                for y in top..bottom {
+                    let start = (y as usize * width as usize + left as usize) * 4;
+                    for pixel in frame[start..start + (right - left) as usize * 4].chunks_exact(4) {
+                        sum += (pixel[0] as u64 * 29 + pixel[1] as u64 * 150 + pixel[2] as u64 * 77) >> 8;
+                    }
+                }
+                let count = ((bottom - top) * (right - left)).max(1) as u64;
+                cells.push((sum / count) as u8);
+            }
+        }
+        Fingerprint { region, columns, rows, cells }
+    }
+
+    /// Compares two fingerprints of the same region.
+    pub fn compare(&self, other: &Fingerprint) -> Difference {
+        let mut changed = 0;
+        let mut bounds: Option<[i32; 4]> = None;
+        for (index, (a, b)) in self.cells.iter().zip(&other.cells).enumerate() {
+            if a.abs_diff(*b) <= CELL_TOLERANCE {
+                continue;
+            }
+            changed += 1;
+            let (column, row) = (index as u32 % self.columns, index as u32 / self.columns);
+            let (left, right) = span(self.region.x, self.region.width, self.columns, column);
+            let (top, bottom) = span(self.region.y, self.region.height, self.rows, row);
+            let cell = [left as i32, top as i32, right as i32, bottom as i32];
+            bounds = Some(bounds.map_or(cell, |b| [b[0].min(cell[0]), b[1].min(cell[1]), b[2].max(cell[2]), b[3].max(cell[3])]));
+        }
+        Difference {
+            changed_fraction: changed as f32 / self.cells.len().max(1) as f32,
+            changed_rect: bounds,
+        }
+    }
+}
+
+/// Pixel range `[start, end)` of cell `index` when `length` pixels from `origin` are split into `count` cells.
+fn span(origin: u32, length: u32, count: u32, index: u32) -> (u32, u32) {
+    (origin + length * index / count, origin + length * (index + 1) / count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 200;
+    const HEIGHT: u32 = 100;
+
+    fn gray(value: u8) -> Vec<u8> {
+        [value, value, value, 0xFF].repeat((WIDTH * HEIGHT) as usize)
+    }
+
+    fn paint(frame: &mut [u8], [left, top, right, bottom]: [u32; 4], value: u8) {
+        for y in top..bottom {
+            for x in left..right {
+                let offset = ((y * WIDTH + x) * 4) as usize;
+                frame[offset..offset + 3].fill(value);
+            }
+        }
+    }
+
+    #[test]
+    fn identical_frames_do_not_differ() {
+        let frame = gray(0x80);
+        let region = Region { x: 0, y: 0, width: WIDTH, height: HEIGHT };
+        let difference = Fingerprint::of(&frame, WIDTH, region).compare(&Fingerprint::of(&frame, WIDTH, region));
+        assert_eq!(difference.changed_fraction, 0.0);
+        assert_eq!(difference.changed_rect, None);
+    }
+
+    #[test]
+    fn changes_within_tolerance_are_ignored() {
+        let before = gray(0x80);
+        let mut after = before.clone();
+        paint(&mut after, [0, 0, WIDTH, HEIGHT], 0x80 + CELL_TOLERANCE);
+        let region = Region { x: 0, y: 0, width: WIDTH, height: HEIGHT };
+        assert_eq!(Fingerprint::of(&before, WIDTH, region).compare(&Fingerprint::of(&after, WIDTH, region)).changed_fraction, 0.0);
+    }
+
+    #[test]
+    fn changed_rect_is_in_screen_coordinates() {
+        let before = gray(0x20);
+        let mut after = before.clone();
+        paint(&mut after, [100, 40, 116, 56], 0xF0);
+        // 160x80 pixels from (40, 20) split 32 ways gives 5x2.5 pixel cells.
+        let region = Region { x: 40, y: 20, width: 160, height: 80 };
+
+        let difference = Fingerprint::of(&before, WIDTH, region).compare(&Fingerprint::of(&after, WIDTH, region));
+        let [left, top, right, bottom] = difference.changed_rect.unwrap();
+        assert!(left <= 100 && top <= 40 && right >= 116 && bottom >= 56);
+        assert!(left >= 95 && top >= 37 && right <= 120 && bottom <= 60);
+        assert!(difference.changed_fraction > 0.0 && difference.changed_fraction < 0.1);
+    }
+
+    #[test]
+    fn changes_outside_the_region_are_ignored() {
+        let before = gray(0x20);
+        let mut after = before.clone();
+        paint(&mut after, [0, 0, 50, 50], 0xF0);
+        let region = Region { x: 100, y: 0, width: 100, height: 100 };
+        assert_eq!(Fingerprint::of(&before, WIDTH, region).compare(&Fingerprint::of(&after, WIDTH, region)).changed_rect, None);
+    }
+
+    #[test]
+    fn small_regions_use_one_cell_per_pixel() {
+        let region = Region { x: 3, y: 4, width: 10, height: 5 };
+        let fingerprint = Fingerprint::of(&gray(0), WIDTH, region);
+        assert_eq!((fingerprint.columns, fingerprint.rows, fingerprint.cells.len()), (10, 5, 50));
+    }
+
+    #[test]
+    fn spans_tile_the_length_exactly() {
+        for (length, count) in [(100, 32), (31, 32), (1000, 7), (5, 5)] {
+            let spans: Vec<_> = (0..count).map(|index| span(10, length, count, index)).collect();
+            assert_eq!(spans[0].0, 10);
+            assert_eq!(spans[count as usize - 1].1, 10 + length);
+            assert!(spans.windows(2).all(|pair| pair[0].1 == pair[1].0));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use super::marks::{Canvas, label_size};
+
+/// Line and label colors in the frame's BGRA byte order.
+const LINE: [u8; 4] = [0xFF, 0x00, 0xFF, 0xFF]; // magenta, rare in UI chrome
+const LABEL_BACKGROUND: [u8; 4] = [0x40, 0x00, 0x40, 0xFF];
+/// Candidate spacings in screen pixels, so labels read as round numbers.
+const STEPS: [u32; 12] = [1, 2, 5, 10, 20, 25, 50, 100, 200, 250, 500, 1000];
+const LABEL_MARGIN: i32 = 2;
+/// Longest side a zoomed image aims for when no scale is given.
+const ZOOM_TARGET: u32 = 1024;
+/// Longest side a zoomed image may have.
+const ZOOM_LIMIT: u32 = 4096;
+const MAX_ZOOM: u32 = 16;
+
+/// A coordinate grid over an image showing part of the screen.
+#[derive(Clone, Copy, Debug)]
+pub struct GridSpec {
+    /// Screen coordinates of the image's top-left pixel.
+    pub origin: [u32; 2],
+    /// Image pixels per screen pixel.
+    pub scale: f32,
+    /// Distance between lines in screen pixels.
+    pub step: u32,
+}
+
+/// Smallest round step whose lines land at least `min_gap` image pixels apart.
+pub fn auto_step(scale: f32, min_gap: f32) -> u32 {
+    STEPS.into_iter().find(|step| *step as f32 * scale >= min_gap).unwrap_or(STEPS[STEPS.len() - 1])
+}
+
+/// Draws grid lines at multiples of `spec.step` screen pixels, labeled with their screen
+/// coordinate, onto a tightly packed BGRA image.
+pub fn draw_grid(image: &mut [u8], width: u32, height: u32, spec: &GridSpec) {
+    let step = spec.step.max(1);
+    let lines = |origin: u32, length: u32| -> Vec<(u32, i32)> {
+        let first = origin.div_ceil(step) * step;
+        let last = origin + (length as f32 / spec.scale) as u32;
+        (first..=last).step_by(step as usize).map(|c| (c, ((c - origin) as f32 * spec.scale).round() as i32)).collect()
+    };
+    let columns = lines(spec.origin[0], width);
+    let rows = lines(spec.origin[1], height);
+
+    let mut canvas = Canvas::new(image, width, height);
+    for &(_, x) in &columns {
+        canvas.tint(x, 0, x + 1, height as i32, LINE);
+    }
+    for &(_, y) in &rows {
+        canvas.tint(0, y, width as i32, y + 1, LINE);
+    }
+
+    // Dense grids label every n-th line so the numbers never overlap.
+    let widest = columns.last().map_or(0, |&(c, _)| label_size(c as usize).0) + LABEL_MARGIN;
+    let gap = step as f32 * spec.scale;
+    let every = |extent: i32| ((extent as f32 / gap).ceil() as usize).max(1);
+    let tall = label_size(0).1 + LABEL_MARGIN;
+
+    for &(c, x) in columns.iter().filter(|(c, _)| ((c / step) as usize).is_multiple_of(every(widest))) {
+        canvas.label(x + LABEL_MARGIN, LABEL_MARGIN, c as usize, LABEL_BACKGROUND);
+    }
+    // Row labels start below the column labels' strip.
+    for &(c, y) in rows.iter().filter(|(c, y)| ((c / step) as usize).is_multiple_of(every(tall)) && *y >= tall) {
+        canvas.label(LABEL_MARGIN, y + LABEL_MARGIN, c as usize, LABEL_BACKGROUND);
+    }
+}
+
+/// Integer magnification for a region, so every screen pixel becomes a whole block of image pixels.
+pub fn zoom_scale(width: u32, height: u32, requested: Option<u32>) -> u32 {
+    let longest = width.max(height).max(1);
+    let scale = requested.unwrap_or(ZOOM_TARGET / longest);
+    scale.clamp(1, MAX_ZOOM).min((ZOOM_LIMIT / longest).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_step_picks_the_smallest_round_step_that_fits() {
+        assert_eq!(auto_step(1.0, 60.0), 100);
+        assert_eq!(auto_step(2.0, 60.0), 50);
+        assert_eq!(auto_step(3.0, 60.0), 20);
+        assert_eq!(auto_step(16.0, 60.0), 5);
+        assert_eq!(auto_step(60.0, 60.0), 1);
+    }
+
+    #[test]
+    fn auto_step_falls_back_to_the_largest_step() {
+        assert_eq!(auto_step(0.01, 60.0), 1000);
+    }
+
+    #[test]
+    fn zoom_scale_targets_a_readable_size() {
+        assert_eq!(zoom_scale(100, 50, None), 10);
+        assert_eq!(zoom_scale(2000, 100, None), 1);
+        assert_eq!(zoom_scale(10, 10, None), MAX_ZOOM);
+    }
+
+    #[test]
+    fn zoom_scale_clamps_requests() {
+        assert_eq!(zoom_scale(100, 100, Some(4)), 4);
+        assert_eq!(zoom_scale(100, 100, Some(0)), 1);
+        assert_eq!(zoom_scale(100, 100, Some(100)), MAX_ZOOM);
+        // 600 * 8 would exceed the 4096 pixel limit.
+        assert_eq!(zoom_scale(600, 10, Some(8)), 6);
+        assert_eq!(zoom_scale(5000, 10, Some(2)), 1);
+    }
+
+    #[test]
+    fn zoom_scale_handles_empty_regions() {
+        assert_eq!(zoom_scale(0, 0, None), MAX_ZOOM);
+    }
+}
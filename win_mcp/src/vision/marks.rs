@@ -0,0 +1,146 @@
+use serde::Serialize;
+use crate::inspector::UiElement;
+
+/// A numbered box drawn on a screenshot, so the model can refer to a target by number.
+#[derive(Serialize, Clone, Debug)]
+pub struct Mark {
+    pub id: usize,
+    pub rect: [i32; 4], // [left, top, right, bottom]
+    pub center: [i32; 2],
+    /// "ui" for inspector elements, "ocr" for recognized words.
+    pub source: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element: Option<UiElement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl Mark {
+    pub fn new(id: usize, rect: [i32; 4], source: &'static str) -> Self {
+        Mark {
+            id,
+            rect,
+            center: [(rect[0] + rect[2]) / 2, (rect[1] + rect[3]) / 2],
+            source,
+            element: None,
+            text: None,
+        }
+    }
+}
+
+/// Outline colors in the frame's BGRA byte order, cycled so neighbouring marks differ.
+const PALETTE: [[u8; 4]; 6] = [
+    [0x00, 0x00, 0xFF, 0xFF], // red
+    [0xFF, 0x60, 0x00, 0xFF], // blue
+    [0x00, 0xA0, 0x00, 0xFF], // green
+    [0xC0, 0x00, 0xC0, 0xFF], // magenta
+    [0x00, 0x80, 0xFF, 0xFF], // orange
+    [0x80, 0x80, 0x00, 0xFF], // teal
+];
+const WHITE: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+
+/// 3x5 digit glyphs, one bit per pixel, row-major from the top-left (bit 14).
+const DIGITS: [u16; 10] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_001_001_001,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111,
+];
+const GLYPH_SCALE: i32 = 2;
+const GLYPH_WIDTH: i32 = 3 * GLYPH_SCALE;
+const GLYPH_HEIGHT: i32 = 5 * GLYPH_SCALE;
+const PADDING: i32 = 2;
+const BORDER: i32 = 2;
+
+/// Draws every mark's box and number onto a tightly packed BGRA frame.
+pub fn draw_marks(frame: &mut [u8], width: u32, height: u32, marks: &[Mark]) {
+    let mut canvas = Canvas::new(frame, width, height);
+    for mark in marks {
+        let color = PALETTE[(mark.id - 1) % PALETTE.len()];
+        let [left, top, right, bottom] = mark.rect;
+        canvas.outline(left, top, right, bottom, color);
+
+        // The label sits above the box, or inside it when the box touches the top edge.
+        let (_, label_height) = label_size(mark.id);
+        let label_top = if top - label_height >= 0 { top - label_height } else { top };
+        canvas.label(left, label_top, mark.id, color);
+    }
+}
+
+/// Pixel size of a number label drawn by `Canvas::label`.
+pub fn label_size(value: usize) -> (i32, i32) {
+    let digits = value.to_string().len() as i32;
+    (digits * (GLYPH_WIDTH + PADDING) + PADDING, GLYPH_HEIGHT + 2 * PADDING)
+}
+
+/// Drawing primitives over a tightly packed BGRA frame; everything is clipped to it.
+pub struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    width: i32,
+    height: i32,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(pixels: &'a mut [u8], width: u32, height: u32) -> Self {
+        Canvas { pixels, width: width as i32, height: height as i32 }
+    }
+
+    pub fn fill(&mut self, left: i32, top: i32, right: i32, bottom: i32, color: [u8; 4]) {
+        let (left, right) = (left.max(0), right.min(self.width));
+        let (top, bottom) = (top.max(0), bottom.min(self.height));
+        for y in top..bottom {
+            for x in left..right {
+                let offset = ((y * self.width + x) * 4) as usize;
+                self.pixels[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Mixes `color` half-and-half into the pixels, so thin lines stay readable over any content.
+    pub fn tint(&mut self, left: i32, top: i32, right: i32, bottom: i32, color: [u8; 4]) {
+        let (left, right) = (left.max(0), right.min(self.width));
+        let (top, bottom) = (top.max(0), bottom.min(self.height));
+        for y in top..bottom {
+            for x in left..right {
+                let offset = ((y * self.width + x) * 4) as usize;
+                for (channel, value) in self.pixels[offset..offset + 3].iter_mut().zip(color) {
+                    *channel = ((*channel as u16 + value as u16) / 2) as u8;
+                }
+            }
+        }
+    }
+
+    pub fn outline(&mut self, left: i32, top: i32, right: i32, bottom: i32, color: [u8; 4]) {
+        self.fill(left, top, right, top + BORDER, color);
+        self.fill(left, bottom - BORDER, right, bottom, color);
+        self.fill(left, top, left + BORDER, bottom, color);
+        self.fill(right - BORDER, top, right, bottom, color);
+    }
+
+    /// White digits of `value` on a filled `background` box with its top-left corner at (`left`, `top`).
+    pub fn label(&mut self, left: i32, top: i32, value: usize, background: [u8; 4]) {
+        let (label_width, label_height) = label_size(value);
+        self.fill(left, top, left + label_width, top + label_height, background);
+        for (i, digit) in value.to_string().bytes().enumerate() {
+            self.glyph(left + PADDING + i as i32 * (GLYPH_WIDTH + PADDING), top + PADDING, DIGITS[(digit - b'0') as usize], WHITE);
+        }
+    }
+
+    fn glyph(&mut self, left: i32, top: i32, glyph: u16, color: [u8; 4]) {
+        for row in 0..5 {
+            for column in 0..3 {
+                if glyph & (1 << (14 - (row * 3 + column))) != 0 {
+                    let x = left + column * GLYPH_SCALE;
+                    let y = top + row * GLYPH_SCALE;
+                    self.fill(x, y, x + GLYPH_SCALE, y + GLYPH_SCALE, color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Result, Context, anyhow};
+use image::{ImageBuffer, Rgba, imageops};
+use super::region::{Region, crop};
+use serde::Serialize;
+use windows::Globalization::Language;
+use windows::Graphics::Imaging::{BitmapPixelFormat, SoftwareBitmap};
+use windows::Media::Ocr::OcrEngine;
+use windows::Storage::Streams::DataWriter;
+use windows::core::HSTRING;
+
+#[derive(Serialize, Debug)]
+pub struct OcrWord {
+    pub text: String,
+    pub rect: [i32; 4], // [left, top, right, bottom]
+    /// Always `None` with the Windows engine, which does not score its results.
+    pub confidence: Option<f32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OcrLine {
+    pub text: String,
+    pub rect: [i32; 4],
+    pub words: Vec<OcrWord>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OcrOutput {
+    pub language: String,
+    pub lines: Vec<OcrLine>,
+}
+
+/// Offline text recognition backed by the OCR engine built into Windows 10+
+/// (Windows.Media.Ocr). It uses the installed language packs and never touches the network.
+/// The engine's calls wait on WinRT async operations, so use it from a blocking thread.
+pub struct TextRecognizer {
+    engine: OcrEngine,
+}
+
+impl TextRecognizer {
+    /// `language` is a BCP-47 tag such as "en-US"; `None` uses the user profile languages.
+    pub fn new(language: Option<&str>) -> Result<Self> {
+        let engine = match language {
+            Some(tag) => {
+                let language = Language::CreateLanguage(&HSTRING::from(tag)).context("Invalid language tag")?;
+                OcrEngine::TryCreateFromLanguage(&language)
+                    .map_err(|_| anyhow!("No OCR language pack installed for {}", tag))?
+            }
+            None => OcrEngine::TryCreateFromUserProfileLanguages()
+                .map_err(|_| anyhow!("No OCR language pack installed for the user profile languages"))?,
+        };
+        Ok(Self { engine })
+    }
+
+    /// Recognizes text in a BGRA frame. `scale` upsamples the image first, which helps
+    /// with small UI fonts; returned rectangles are always in frame coordinates.
+    pub fn recognize(&self, bgra: &[u8], width: u32, height: u32, region: Option<Region>, scale: u32) -> Result<OcrOutput> {
+        if bgra.len() != width as usize * height as usize * 4 {
+            return Err(anyhow!("Frame buffer does not match {}x{}", width, height));
+        }
+        let region = region.unwrap_or(Region { x: 0, y: 0, width, height });
+        if region.width == 0
+            || region.height == 0
+            || region.x.checked_add(region.width).is_none_or(|right| right > width)
+            || region.y.checked_add(region.height).is_none_or(|bottom| bottom > height)
+        {
+            return Err(anyhow!("Region is empty or outside the {}x{} screen", width, height));
+        }
+
+        let scale = scale.clamp(1, 4);
+        let mut image = crop(bgra, width, region);
+        if scale > 1 {
+            image = imageops::resize(&image, region.width * scale, region.height * scale, imageops::FilterType::CatmullRom);
+        }
+
+        let max_dimension = OcrEngine::MaxImageDimension().unwrap_or(u32::MAX);
+        if image.width() > max_dimension || image.height() > max_dimension {
+            return Err(anyhow!("Image exceeds the OCR limit of {} pixels per side; use a region or a smaller scale", max_dimension));
+        }
+
+        let bitmap = software_bitmap(&image)?;
+        let result = self.engine.RecognizeAsync(&bitmap)?.get().context("OCR failed")?;
+
+        // Maps a rect of the scaled crop back to frame pixels.
+        let to_frame = |rect: windows::Foundation::Rect| -> [i32; 4] {
+            let s = scale as f32;
+            [
+                region.x as i32 + (rect.X / s).floor() as i32,
+                region.y as i32 + (rect.Y / s).floor() as i32,
+                region.x as i32 + ((rect.X + rect.Width) / s).ceil() as i32,
+                region.y as i32 + ((rect.Y + rect.Height) / s).ceil() as i32,
+            ]
+        };
+
+        let mut lines = Vec::new();
+        for line in result.Lines()? {
+            let words: Vec<OcrWord> = line
+                .Words()?
+                .into_iter()
+                .map(|word| -> Result<OcrWord> {
+                    Ok(OcrWord { text: word.Text()?.to_string(), rect: to_frame(word.BoundingRect()?), confidence: None })
+                })
+                .collect::<Result<_>>()?;
+            lines.push(OcrLine { text: line.Text()?.to_string(), rect: union(&words), words });
+        }
+
+        Ok(OcrOutput {
+            language: self.engine.RecognizerLanguage()?.LanguageTag()?.to_string(),
+            lines,
+        })
+    }
+}
+
+fn software_bitmap(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Result<SoftwareBitmap> {
+    let writer = DataWriter::new()?;
+    writer.WriteBytes(image.as_raw())?;
+    let buffer = writer.DetachBuffer()?;
+    // The pixels are still in the capture's BGRA order; only the channel labels say RGBA.
+    SoftwareBitmap::CreateCopyFromBuffer(&buffer, BitmapPixelFormat::Bgra8, image.width() as i32, image.height() as i32)
+        .context("Failed to create bitmap for OCR")
+}
+
+fn union(words: &[OcrWord]) -> [i32; 4] {
+    words.iter().fold([i32::MAX, i32::MAX, i32::MIN, i32::MIN], |acc, word| {
+        [acc[0].min(word.rect[0]), acc[1].min(word.rect[1]), acc[2].max(word.rect[2]), acc[3].max(word.rect[3])]
+    })
+}
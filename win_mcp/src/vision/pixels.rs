@@ -0,0 +1,143 @@
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use super::region::Region;
+
+/// Bits kept per channel when bucketing colors for the dominant-color histogram.
+const BUCKET_BITS: u32 = 4;
+const MAX_DOMINANT: usize = 3;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub rgb: [u8; 3],
+    pub hex: String,
+}
+
+impl Color {
+    pub fn new(rgb: [u8; 3]) -> Self {
+        Color { rgb, hex: format!("#{:02X}{:02X}{:02X}", rgb[0], rgb[1], rgb[2]) }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PixelSample {
+    pub point: [u32; 2],
+    pub color: Color,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DominantColor {
+    /// Average of the pixels that fell into this color bucket.
+    pub color: Color,
+    /// Share of the region's pixels in the bucket, 0.0 to 1.0.
+    pub share: f32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RegionStats {
+    pub rect: [u32; 4], // [left, top, right, bottom]
+    pub mean: Color,
+    pub dominant: Vec<DominantColor>,
+}
+
+/// Color of one pixel in a tightly packed BGRA frame `width` pixels wide.
+pub fn sample(frame: &[u8], width: u32, x: u32, y: u32) -> PixelSample {
+    let offset = (y as usize * width as usize + x as usize) * 4;
+    let pixel = &frame[offset..offset + 4];
+    PixelSample { point: [x, y], color: Color::new([pixel[2], pixel[1], pixel[0]]) }
+}
+
+/// Mean color and the most common colors of `region`, which must lie inside the frame.
+pub fn region_stats(frame: &[u8], width: u32, region: Region) -> RegionStats {
+    let mut total = [0u64; 3];
+    // Bucket key -> (pixel count, channel sums). Ordered by key so equal counts rank the same every time.
+    let mut buckets: BTreeMap<u32, (u64, [u64; 3])> = BTreeMap::new();
+    let shift = 8 - BUCKET_BITS;
+
+    for y in region.y..region.y + region.height {
+        let start = (y as usize * width as usize + region.x as usize) * 4;
+        for pixel in frame[start..start + region.width as usize * 4].chunks_exact(4) {
+            let rgb = [pixel[2] as u64, pixel[1] as u64, pixel[0] as u64];
+            let key = (pixel[2] as u32 >> shift) << (2 * BUCKET_BITS) | (pixel[1] as u32 >> shift) << BUCKET_BITS | pixel[0] as u32 >> shift;
+            let bucket = buckets.entry(key).or_default();
+            bucket.0 += 1;
+            for channel in 0..3 {
+                bucket.1[channel] += rgb[channel];
+                total[channel] += rgb[channel];
+            }
+        }
+    }
+
+    let count = (region.width as u64 * region.height as u64).max(1);
+    let average = |sums: [u64; 3], n: u64| Color::new(sums.map(|sum| (sum / n.max(1)) as u8));
+
+    let mut ranked: Vec<(u64, [u64; 3])> = buckets.into_values().collect();
+    ranked.sort_by_key(|&(n, _)| Reverse(n));
+    let dominant = ranked
+        .into_iter()
+        .take(MAX_DOMINANT)
+        .map(|(n, sums)| DominantColor { color: average(sums, n), share: n as f32 / count as f32 })
+        .collect();
+
+    RegionStats {
+        rect: [region.x, region.y, region.x + region.width, region.y + region.height],
+        mean: average(total, count),
+        dominant,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 4;
+
+    /// A 4x2 BGRA frame from RGB pixels, row by row.
+    fn frame(rgb: [[u8; 3]; 8]) -> Vec<u8> {
+        rgb.iter().flat_map(|&[r, g, b]| [b, g, r, 0xFF]).collect()
+    }
+
+    #[test]
+    fn sample_reads_rgb_from_bgra() {
+        let mut pixels = [[0; 3]; 8];
+        pixels[6] = [0x12, 0x34, 0x56];
+        let sample = sample(&frame(pixels), WIDTH, 2, 1);
+        assert_eq!(sample.point, [2, 1]);
+        assert_eq!(sample.color, Color::new([0x12, 0x34, 0x56]));
+        assert_eq!(sample.color.hex, "#123456");
+    }
+
+    #[test]
+    fn stats_cover_only_the_region() {
+        let mut pixels = [[0xFF, 0, 0]; 8];
+        pixels[1] = [0, 0, 0xFF];
+        pixels[2] = [0, 0, 0xFF];
+        let stats = region_stats(&frame(pixels), WIDTH, Region { x: 1, y: 0, width: 2, height: 1 });
+        assert_eq!(stats.rect, [1, 0, 3, 1]);
+        assert_eq!(stats.mean, Color::new([0, 0, 0xFF]));
+        assert_eq!(stats.dominant.len(), 1);
+        assert_eq!(stats.dominant[0].share, 1.0);
+    }
+
+    #[test]
+    fn dominant_colors_rank_by_share() {
+        let white = [0xFF; 3];
+        let red = [0xFF, 0, 0];
+        let blue = [0, 0, 0xFF];
+        let black = [0; 3];
+        let pixels = [white, white, white, white, red, red, blue, black];
+        let stats = region_stats(&frame(pixels), WIDTH, Region { x: 0, y: 0, width: 4, height: 2 });
+
+        assert_eq!(stats.mean, Color::new([0xBF, 0x7F, 0x9F]));
+        let ranked: Vec<_> = stats.dominant.iter().map(|d| (d.color.rgb, d.share)).collect();
+        // Blue and black tie; the lower bucket key (black) wins every time.
+        assert_eq!(ranked, [(white, 0.5), (red, 0.25), (black, 0.125)]);
+    }
+
+    #[test]
+    fn dominant_color_averages_its_bucket() {
+        let pixels = [[0x40, 0x40, 0x40], [0x42, 0x44, 0x46], [0x40; 3], [0x42, 0x44, 0x46], [0; 3], [0; 3], [0; 3], [0; 3]];
+        let stats = region_stats(&frame(pixels), WIDTH, Region { x: 0, y: 0, width: 4, height: 1 });
+        assert_eq!(stats.dominant[0].color, Color::new([0x41, 0x42, 0x43]));
+    }
+}
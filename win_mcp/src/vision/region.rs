@@ -0,0 +1,57 @@
+use image::{ImageBuffer, Rgba};
+
+/// Screen-space rectangle, in pixels of the captured output.
+#[derive(Clone, Copy, Debug)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// Smallest region containing both.
+    pub fn union(self, other: Region) -> Region {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Region { x, y, width: right - x, height: bottom - y }
+    }
+}
+
+/// Copies `region` out of a tightly packed 4-byte-per-pixel frame.
+pub fn crop(frame: &[u8], frame_width: u32, region: Region) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let stride = frame_width as usize * 4;
+    let mut pixels = Vec::with_capacity(region.width as usize * region.height as usize * 4);
+    for y in region.y..region.y + region.height {
+        let start = y as usize * stride + region.x as usize * 4;
+        pixels.extend_from_slice(&frame[start..start + region.width as usize * 4]);
+    }
+    ImageBuffer::from_raw(region.width, region.height, pixels).expect("crop buffer matches its dimensions")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_covers_both_regions() {
+        let a = Region { x: 10, y: 20, width: 5, height: 5 };
+        let b = Region { x: 2, y: 22, width: 4, height: 10 };
+        let union = a.union(b);
+        assert_eq!((union.x, union.y, union.width, union.height), (2, 20, 13, 12));
+        let inside = Region { x: 11, y: 21, width: 1, height: 1 };
+        let same = a.union(inside);
+        assert_eq!((same.x, same.y, same.width, same.height), (10, 20, 5, 5));
+    }
+
+    #[test]
+    fn crop_copies_the_rows_of_the_region() {
+        // 3x2 frame whose pixels hold their own index in every channel.
+        let frame: Vec<u8> = (0..6u8).flat_map(|i| [i; 4]).collect();
+        let image = crop(&frame, 3, Region { x: 1, y: 0, width: 2, height: 2 });
+        assert_eq!((image.width(), image.height()), (2, 2));
+        let firsts: Vec<u8> = image.pixels().map(|p| p.0[0]).collect();
+        assert_eq!(firsts, [1, 2, 4, 5]);
+    }
+}
@@ -0,0 +1,450 @@
+use serde::Serialize;
+
+/// Grayscale image with `f32` intensities, row-major.
+#[derive(Clone, Debug)]
+pub struct Plane {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<f32>,
+}
+
+impl Plane {
+    pub fn new(width: usize, height: usize, data: Vec<f32>) -> Self {
+        assert_eq!(data.len(), width * height, "plane data does not match its dimensions");
+        Plane { width, height, data }
+    }
+
+    /// Luma of a tightly packed BGRA frame (the layout `DesktopCapture` returns).
+    pub fn from_bgra(bgra: &[u8], width: usize, height: usize) -> Self {
+        let data = bgra.chunks_exact(4).map(|p| luma(p[2], p[1], p[0])).collect();
+        Plane::new(width, height, data)
+    }
+
+    pub fn from_rgba(rgba: &[u8], width: usize, height: usize) -> Self {
+        let data = rgba.chunks_exact(4).map(|p| luma(p[0], p[1], p[2])).collect();
+        Plane::new(width, height, data)
+    }
+
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Plane {
+        let data = (y..y + height).flat_map(|row| self.data[row * self.width + x..][..width].iter().copied()).collect();
+        Plane::new(width, height, data)
+    }
+
+    fn at(&self, x: usize, y: usize) -> f32 {
+        self.data[y * self.width + x]
+    }
+
+    /// Box-filtered downsample by an integer factor.
+    fn downsample(&self, factor: usize) -> Plane {
+        if factor == 1 {
+            return self.clone();
+        }
+        let (width, height) = (self.width / factor, self.height / factor);
+        let area = (factor * factor) as f32;
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0.0;
+                for dy in 0..factor {
+                    for dx in 0..factor {
+                        sum += self.at(x * factor + dx, y * factor + dy);
+                    }
+                }
+                data.push(sum / area);
+            }
+        }
+        Plane::new(width, height, data)
+    }
+
+    /// Bilinear resize to `width` x `height`.
+    fn resize(&self, width: usize, height: usize) -> Plane {
+        let sx = self.width as f32 / width as f32;
+        let sy = self.height as f32 / height as f32;
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let fy = ((y as f32 + 0.5) * sy - 0.5).clamp(0.0, (self.height - 1) as f32);
+            let (y0, ty) = (fy.floor() as usize, fy.fract());
+            let y1 = (y0 + 1).min(self.height - 1);
+            for x in 0..width {
+                let fx = ((x as f32 + 0.5) * sx - 0.5).clamp(0.0, (self.width - 1) as f32);
+                let (x0, tx) = (fx.floor() as usize, fx.fract());
+                let x1 = (x0 + 1).min(self.width - 1);
+                let top = self.at(x0, y0) * (1.0 - tx) + self.at(x1, y0) * tx;
+                let bottom = self.at(x0, y1) * (1.0 - tx) + self.at(x1, y1) * tx;
+                data.push(top * (1.0 - ty) + bottom * ty);
+            }
+        }
+        Plane::new(width, height, data)
+    }
+}
+
+fn luma(r: u8, g: u8, b: u8) -> f32 {
+    0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchOptions {
+    /// Minimum normalized cross-correlation, from -1 to 1.
+    pub threshold: f32,
+    pub max_results: usize,
+    /// Template scale factors to try, e.g. `[1.0, 1.25, 1.5]` for DPI differences.
+    pub scales: Vec<f32>,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions { threshold: 0.8, max_results: 5, scales: vec![1.0] }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TemplateMatch {
+    pub rect: [i32; 4], // [left, top, right, bottom] in haystack pixels
+    pub score: f32,
+    pub scale: f32,
+}
+
+/// Smallest template side kept when searching on a downsampled pyramid level.
+const MIN_COARSE_SIDE: usize = 8;
+const MAX_PYRAMID_FACTOR: usize = 8;
+const COARSE_CANDIDATES_PER_RESULT: usize = 16;
+/// Matches overlapping more than this fraction of the smaller one are duplicates.
+const MAX_OVERLAP: f32 = 0.3;
+
+/// Finds occurrences of `template` in `haystack` by zero-mean normalized
+/// cross-correlation, best score first.
+///
+/// Each scale is searched coarse-to-fine: a full scan on a downsampled pyramid
+/// level proposes candidates, which are then refined at full resolution.
+pub fn find_template(haystack: &Plane, template: &Plane, options: &MatchOptions) -> Vec<TemplateMatch> {
+    let mut matches = Vec::new();
+    for &scale in &options.scales {
+        let width = (template.width as f32 * scale).round() as usize;
+        let height = (template.height as f32 * scale).round() as usize;
+        if width == 0 || height == 0 || width > haystack.width || height > haystack.height {
+            continue;
+        }
+        let scaled = if scale == 1.0 { template.clone() } else { template.resize(width, height) };
+        matches.extend(search_scale(haystack, &scaled, scale, options));
+    }
+
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suppress_overlaps(matches, options.max_results)
+}
+
+fn search_scale(haystack: &Plane, template: &Plane, scale: f32, options: &MatchOptions) -> Vec<TemplateMatch> {
+    let mut factor = 1;
+    while factor < MAX_PYRAMID_FACTOR && template.width.min(template.height) / (factor * 2) >= MIN_COARSE_SIDE {
+        factor *= 2;
+    }
+
+    let found: Vec<(usize, usize, f32)> = if factor == 1 {
+        let scorer = Scorer::new(haystack, template);
+        scorer.scan().into_iter().filter(|&(_, _, score)| score >= options.threshold).collect()
+    } else {
+        let coarse_haystack = haystack.downsample(factor);
+        let coarse_template = template.downsample(factor);
+        let coarse = Scorer::new(&coarse_haystack, &coarse_template);
+        let fine = Scorer::new(haystack, template);
+
+        // Downsampling blurs detail and misaligns the grid, so coarse scores run well below
+        // full-resolution ones; rank candidates instead of thresholding them. Only local maxima
+        // compete, so the slopes around one strong peak cannot crowd out other matches.
+        let radius = (coarse_template.width.min(coarse_template.height) / 2).max(1);
+        let mut candidates = local_maxima(&coarse.scan(), coarse.max_x() + 1, radius);
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+        candidates.truncate(options.max_results.max(1) * COARSE_CANDIDATES_PER_RESULT);
+
+        candidates
+            .into_iter()
+            .filter_map(|(cx, cy, _)| fine.refine(cx * factor, cy * factor, factor))
+            .filter(|&(_, _, score)| score >= options.threshold)
+            .collect()
+    };
+
+    let mut matches: Vec<TemplateMatch> = found
+        .into_iter()
+        .map(|(x, y, score)| TemplateMatch {
+            rect: [x as i32, y as i32, (x + template.width) as i32, (y + template.height) as i32],
+            score,
+            scale,
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suppress_overlaps(matches, options.max_results)
+}
+
+/// Positions of a row-major `scan` that score highest within `radius` in both directions.
+/// Ties go to the first position in scan order, so a flat plateau yields one candidate.
+fn local_maxima(scores: &[(usize, usize, f32)], columns: usize, radius: usize) -> Vec<(usize, usize, f32)> {
+    let rows = scores.len() / columns;
+    scores
+        .iter()
+        .enumerate()
+        .filter(|&(i, &(x, y, score))| {
+            (y.saturating_sub(radius)..=(y + radius).min(rows - 1)).all(|ny| {
+                (x.saturating_sub(radius)..=(x + radius).min(columns - 1)).all(|nx| {
+                    let j = ny * columns + nx;
+                    let other = scores[j].2;
+                    other < score || (other == score && j >= i)
+                })
+            })
+        })
+        .map(|(_, &candidate)| candidate)
+        .collect()
+}
+
+/// Keeps the best of every group of overlapping matches; `matches` must be sorted by score.
+fn suppress_overlaps(matches: Vec<TemplateMatch>, max_results: usize) -> Vec<TemplateMatch> {
+    let mut kept: Vec<TemplateMatch> = Vec::new();
+    for candidate in matches {
+        if kept.len() >= max_results {
+            break;
+        }
+        if kept.iter().all(|k| overlap(&k.rect, &candidate.rect) <= MAX_OVERLAP) {
+            kept.push(candidate);
+        }
+    }
+    kept
+}
+
+/// Intersection area relative to the smaller rectangle.
+fn overlap(a: &[i32; 4], b: &[i32; 4]) -> f32 {
+    let width = (a[2].min(b[2]) - a[0].max(b[0])).max(0) as f32;
+    let height = (a[3].min(b[3]) - a[1].max(b[1])).max(0) as f32;
+    let area = |r: &[i32; 4]| ((r[2] - r[0]) * (r[3] - r[1])) as f32;
+    width * height / area(a).min(area(b))
+}
+
+/// Scores template positions against one haystack, using integral images for window statistics.
+struct Scorer<'a> {
+    haystack: &'a Plane,
+    /// Template minus its mean; its sum is zero, so the window mean drops out of the numerator.
+    centered: Vec<f32>,
+    template_width: usize,
+    template_height: usize,
+    template_norm: f64,
+    sums: Vec<f64>,
+    squares: Vec<f64>,
+}
+
+impl<'a> Scorer<'a> {
+    fn new(haystack: &'a Plane, template: &Plane) -> Self {
+        let n = template.data.len() as f32;
+        let mean = template.data.iter().sum::<f32>() / n;
+        let centered: Vec<f32> = template.data.iter().map(|v| v - mean).collect();
+        let template_norm = centered.iter().map(|v| (*v as f64).powi(2)).sum::<f64>();
+
+        let stride = haystack.width + 1;
+        let mut sums = vec![0.0; stride * (haystack.height + 1)];
+        let mut squares = vec![0.0; stride * (haystack.height + 1)];
+        for y in 0..haystack.height {
+            let (mut row_sum, mut row_squares) = (0.0, 0.0);
+            for x in 0..haystack.width {
+                let v = haystack.at(x, y) as f64;
+                row_sum += v;
+                row_squares += v * v;
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row_sum;
+                squares[(y + 1) * stride + x + 1] = squares[y * stride + x + 1] + row_squares;
+            }
+        }
+
+        Scorer {
+            haystack,
+            centered,
+            template_width: template.width,
+            template_height: template.height,
+            template_norm,
+            sums,
+            squares,
+        }
+    }
+
+    fn max_x(&self) -> usize {
+        self.haystack.width - self.template_width
+    }
+
+    fn max_y(&self) -> usize {
+        self.haystack.height - self.template_height
+    }
+
+    fn window_sum(table: &[f64], stride: usize, x: usize, y: usize, w: usize, h: usize) -> f64 {
+        table[(y + h) * stride + x + w] - table[y * stride + x + w] - table[(y + h) * stride + x] + table[y * stride + x]
+    }
+
+    /// NCC at top-left `(x, y)`; flat windows or templates score 0.
+    fn score(&self, x: usize, y: usize) -> f32 {
+        let (w, h) = (self.template_width, self.template_height);
+        let stride = self.haystack.width + 1;
+        let n = (w * h) as f64;
+        let sum = Self::window_sum(&self.sums, stride, x, y, w, h);
+        let squares = Self::window_sum(&self.squares, stride, x, y, w, h);
+        let window_norm = squares - sum * sum / n;
+        let denominator = (window_norm * self.template_norm).sqrt();
+        if denominator < 1e-6 {
+            return 0.0;
+        }
+
+        let mut numerator = 0.0f64;
+        for ty in 0..h {
+            let row = &self.haystack.data[(y + ty) * self.haystack.width + x..][..w];
+            let template_row = &self.centered[ty * w..][..w];
+            numerator += row.iter().zip(template_row).map(|(a, b)| a * b).sum::<f32>() as f64;
+        }
+        (numerator / denominator) as f32
+    }
+
+    /// Scores every position.
+    fn scan(&self) -> Vec<(usize, usize, f32)> {
+        let mut scores = Vec::with_capacity((self.max_x() + 1) * (self.max_y() + 1));
+        for y in 0..=self.max_y() {
+            for x in 0..=self.max_x() {
+                scores.push((x, y, self.score(x, y)));
+            }
+        }
+        scores
+    }
+
+    /// Best position within `radius` of `(x, y)`.
+    fn refine(&self, x: usize, y: usize, radius: usize) -> Option<(usize, usize, f32)> {
+        let (x0, x1) = (x.saturating_sub(radius), (x + radius).min(self.max_x()));
+        let (y0, y1) = (y.saturating_sub(radius), (y + radius).min(self.max_y()));
+        (y0..=y1)
+            .flat_map(|py| (x0..=x1).map(move |px| (px, py)))
+            .map(|(px, py)| (px, py, self.score(px, py)))
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic noise, so matches are unambiguous without fixture files.
+    fn noise(width: usize, height: usize, seed: u32) -> Plane {
+        let mut state = seed;
+        let data = (0..width * height)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as f32
+            })
+            .collect();
+        Plane::new(width, height, data)
+    }
+
+    fn paste(plane: &mut Plane, patch: &Plane, x: usize, y: usize) {
+        for row in 0..patch.height {
+            let target = (y + row) * plane.width + x;
+            plane.data[target..target + patch.width].copy_from_slice(&patch.data[row * patch.width..][..patch.width]);
+        }
+    }
+
+    #[test]
+    fn finds_exact_copy() {
+        let haystack = noise(200, 150, 1);
+        let template = haystack.crop(70, 40, 24, 20);
+
+        let matches = find_template(&haystack, &template, &MatchOptions::default());
+        assert_eq!(matches[0].rect, [70, 40, 94, 60]);
+        assert!(matches[0].score > 0.999);
+    }
+
+    #[test]
+    fn refines_coarse_candidates_to_exact_position() {
+        let haystack = noise(320, 240, 2);
+        // Large enough to be searched on a downsampled level, at an odd offset.
+        let template = haystack.crop(133, 87, 48, 40);
+
+        let matches = find_template(&haystack, &template, &MatchOptions::default());
+        assert_eq!(matches[0].rect, [133, 87, 181, 127]);
+    }
+
+    #[test]
+    fn finds_every_copy_up_to_max_results() {
+        let mut haystack = noise(240, 120, 3);
+        let icon = noise(16, 16, 99);
+        for x in [10, 90, 170] {
+            paste(&mut haystack, &icon, x, 50);
+        }
+
+        let options = MatchOptions { max_results: 2, ..MatchOptions::default() };
+        let matches = find_template(&haystack, &icon, &options);
+        assert_eq!(matches.len(), 2);
+
+        let all = find_template(&haystack, &icon, &MatchOptions::default());
+        let mut lefts: Vec<i32> = all.iter().map(|m| m.rect[0]).collect();
+        lefts.sort();
+        assert_eq!(lefts, vec![10, 90, 170]);
+    }
+
+    #[test]
+    fn strong_match_does_not_hide_a_weaker_one() {
+        // Stripes that change slowly along x: every coarse position along the strong match's
+        // row outscores the distorted copy, more of them than the candidate budget holds.
+        let stripes = Plane::new(480, 112, (0..480 * 112)
+            .map(|i| {
+                let (x, y) = ((i % 480) as f32 - 200.0, (i / 480) as f32);
+                128.0 + 80.0 * (y / 6.0).sin() + 0.004 * x * x
+            })
+            .collect());
+        let icon = stripes.crop(168, 24, 64, 64);
+        let distorted = Plane::new(64, 64, icon.data.iter().enumerate()
+            .map(|(i, v)| {
+                let (x, y) = ((i % 64) as f32, (i / 64) as f32);
+                v + 30.0 * (x / 9.0).sin() * (y / 11.0).cos()
+            })
+            .collect());
+        let mut haystack = noise(480, 240, 8);
+        paste(&mut haystack, &stripes, 0, 0);
+        paste(&mut haystack, &distorted, 300, 150);
+
+        let options = MatchOptions { max_results: 2, ..MatchOptions::default() };
+        let matches = find_template(&haystack, &icon, &options);
+        let rects: Vec<[i32; 4]> = matches.iter().map(|m| m.rect).collect();
+        assert_eq!(rects, vec![[168, 24, 232, 88], [300, 150, 364, 214]]);
+        assert!(matches[1].score < matches[0].score);
+    }
+
+    #[test]
+    fn local_maxima_keep_one_position_per_peak() {
+        // 5x1 scan with peaks at 1 and 4 and a plateau tie at 2.
+        let scores: Vec<(usize, usize, f32)> = [0.2, 0.9, 0.9, 0.1, 0.5].iter().enumerate().map(|(x, &s)| (x, 0, s)).collect();
+        let peaks: Vec<usize> = local_maxima(&scores, 5, 1).into_iter().map(|(x, _, _)| x).collect();
+        assert_eq!(peaks, vec![1, 4]);
+    }
+
+    #[test]
+    fn matches_scaled_template() {
+        // Smooth content survives resampling, unlike per-pixel noise.
+        let data = (0..64 * 64)
+            .map(|i| {
+                let (x, y) = ((i % 64) as f32, (i / 64) as f32);
+                128.0 + 60.0 * (x / 5.0).sin() + 60.0 * (y / 7.0).cos()
+            })
+            .collect();
+        let icon = Plane::new(64, 64, data);
+        let mut haystack = noise(300, 200, 4);
+        paste(&mut haystack, &icon, 150, 90);
+        let small = icon.resize(32, 32);
+
+        let options = MatchOptions { scales: vec![1.0, 2.0], ..MatchOptions::default() };
+        let matches = find_template(&haystack, &small, &options);
+        assert_eq!(matches[0].scale, 2.0);
+        let [left, top, _, _] = matches[0].rect;
+        assert!((left - 150).abs() <= 1 && (top - 90).abs() <= 1, "found at {:?}", matches[0].rect);
+    }
+
+    #[test]
+    fn rejects_absent_template() {
+        let haystack = noise(160, 120, 5);
+        let template = noise(20, 20, 6);
+        assert!(find_template(&haystack, &template, &MatchOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn flat_template_matches_nothing() {
+        let haystack = noise(100, 100, 7);
+        let template = Plane::new(10, 10, vec![42.0; 100]);
+        assert!(find_template(&haystack, &template, &MatchOptions::default()).is_empty());
+    }
+}
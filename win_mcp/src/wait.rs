@@ -0,0 +1,155 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_INTERVAL_MS: u64 = 250;
+const MIN_INTERVAL_MS: u64 = 10;
+/// Least time a blocking check is given, so the first check of a short wait can still finish.
+pub const MIN_CHECK_TIME: Duration = Duration::from_secs(1);
+
+pub enum WaitOutcome<T> {
+    Satisfied(T),
+    TimedOut,
+}
+
+pub struct WaitReport<T> {
+    pub outcome: WaitOutcome<T>,
+    pub elapsed: Duration,
+    pub attempts: u32,
+}
+
+/// Reads `timeout_ms` and `interval_ms` from tool arguments.
+pub fn timing_from_args(args: &Value) -> (Duration, Duration) {
+    let timeout = args["timeout_ms"].as_u64().unwrap_or(DEFAULT_TIMEOUT_MS);
+    let interval = args["interval_ms"].as_u64().unwrap_or(DEFAULT_INTERVAL_MS).max(MIN_INTERVAL_MS);
+    (Duration::from_millis(timeout), Duration::from_millis(interval))
+}
+
+/// Calls `check` every `interval` until it yields a value or `timeout` elapses.
+/// The check always runs at least once, even with a zero timeout.
+pub async fn poll_until<T, F>(timeout: Duration, interval: Duration, mut check: F) -> Result<WaitReport<T>>
+where
+    F: FnMut() -> Result<Option<T>>,
+{
+    poll(timeout, interval, |_| std::future::ready(check())).await
+}
+
+/// Like `poll_until`, for checks that block, such as UI Automation searches. Each check runs on
+/// a blocking thread and is handed the time left, at least `MIN_CHECK_TIME`, to bound its calls with.
+pub async fn poll_blocking<T, F>(timeout: Duration, interval: Duration, check: F) -> Result<WaitReport<T>>
+where
+    F: FnMut(Duration) -> Result<Option<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    let check = Arc::new(Mutex::new(check));
+    poll(timeout, interval, |remaining| {
+        let check = check.clone();
+        async move { tokio::task::spawn_blocking(move || (*check.lock().unwrap())(remaining)).await? }
+    }).await
+}
+
+async fn poll<T, F, Fut>(timeout: Duration, interval: Duration, mut attempt: F) -> Result<WaitReport<T>>
+where
+    F: FnMut(Duration) -> Fut,
+    Fut: Future<Output = Result<Option<T>>>,
+{
+    let started = Instant::now();
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        let remaining = timeout.saturating_sub(started.elapsed()).max(MIN_CHECK_TIME);
+        let result = attempt(remaining).await;
+        let elapsed = started.elapsed();
+        match result {
+            Ok(Some(value)) => return Ok(WaitReport { outcome: WaitOutcome::Satisfied(value), elapsed, attempts }),
+            Ok(None) => {}
+            // A check cut short by the time limit fails instead of returning nothing.
+            Err(_) if elapsed >= timeout => return Ok(WaitReport { outcome: WaitOutcome::TimedOut, elapsed, attempts }),
+            Err(e) => return Err(e),
+        }
+
+        if elapsed >= timeout {
+            return Ok(WaitReport { outcome: WaitOutcome::TimedOut, elapsed, attempts });
+        }
+        tokio::time::sleep(interval.min(timeout - elapsed)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    #[tokio::test]
+    async fn returns_the_value_once_the_check_is_met() {
+        let mut calls = 0;
+        let report = poll_until(Duration::from_secs(5), INTERVAL, || {
+            calls += 1;
+            Ok((calls == 3).then_some("ready"))
+        }).await.unwrap();
+        assert!(matches!(report.outcome, WaitOutcome::Satisfied("ready")));
+        assert_eq!(report.attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn times_out_when_the_check_is_never_met() {
+        let report = poll_until(Duration::from_millis(50), INTERVAL, || Ok(None::<()>)).await.unwrap();
+        assert!(matches!(report.outcome, WaitOutcome::TimedOut));
+        assert!(report.elapsed >= Duration::from_millis(50));
+        assert!(report.attempts > 1);
+    }
+
+    #[tokio::test]
+    async fn zero_timeout_still_checks_once() {
+        let report = poll_until(Duration::ZERO, INTERVAL, || Ok(Some(1))).await.unwrap();
+        assert!(matches!(report.outcome, WaitOutcome::Satisfied(1)));
+
+        let report = poll_until(Duration::ZERO, INTERVAL, || Ok(None::<()>)).await.unwrap();
+        assert!(matches!(report.outcome, WaitOutcome::TimedOut));
+        assert_eq!(report.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn check_errors_end_the_wait() {
+        let result = poll_until(Duration::from_secs(5), INTERVAL, || Err::<Option<()>, _>(anyhow!("provider gone"))).await;
+        assert_eq!(result.err().unwrap().to_string(), "provider gone");
+    }
+
+    #[tokio::test]
+    async fn errors_after_the_deadline_count_as_a_timeout() {
+        let report = poll_until(Duration::from_millis(20), INTERVAL, || {
+            std::thread::sleep(Duration::from_millis(30));
+            Err::<Option<()>, _>(anyhow!("transaction timed out"))
+        }).await.unwrap();
+        assert!(matches!(report.outcome, WaitOutcome::TimedOut));
+    }
+
+    #[tokio::test]
+    async fn blocking_checks_get_the_remaining_time() {
+        let mut budgets = Vec::new();
+        let report = poll_blocking(Duration::from_secs(30), INTERVAL, move |remaining| {
+            budgets.push(remaining);
+            Ok((budgets.len() == 2).then(|| budgets.clone()))
+        }).await.unwrap();
+        let WaitOutcome::Satisfied(budgets) = report.outcome else { panic!("wait timed out") };
+        assert!(budgets[0] <= Duration::from_secs(30) && budgets[0] > budgets[1]);
+        assert!(budgets[1] >= MIN_CHECK_TIME);
+    }
+
+    #[tokio::test]
+    async fn blocking_checks_never_get_less_than_the_minimum() {
+        let report = poll_blocking(Duration::ZERO, INTERVAL, |remaining| Ok(Some(remaining))).await.unwrap();
+        assert!(matches!(report.outcome, WaitOutcome::Satisfied(remaining) if remaining == MIN_CHECK_TIME));
+    }
+
+    #[tokio::test]
+    async fn blocking_check_errors_end_the_wait() {
+        let result = poll_blocking(Duration::from_secs(5), INTERVAL, |_| Err::<Option<()>, _>(anyhow!("no desktop"))).await;
+        assert_eq!(result.err().unwrap().to_string(), "no desktop");
+    }
+}
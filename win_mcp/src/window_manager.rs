@@ -0,0 +1,507 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowState {
+    Normal,
+    Minimized,
+    Maximized,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WindowInfo {
+    pub handle: u64,
+    pub title: String,
+    pub process_name: String,
+    pub process_id: u32,
+    pub bounds: [i32; 4], // [left, top, right, bottom]
+    pub monitor: usize,
+    pub z_order: usize, // 0 is the topmost window
+    pub state: WindowState,
+}
+
+/// A visible window owned by another top-level window, such as a message box or a save prompt.
+#[derive(Serialize, Clone, Debug)]
+pub struct DialogWindow {
+    #[serde(flatten)]
+    pub window: WindowInfo,
+    pub owner: u64,
+    pub owner_title: String,
+    /// The owner is disabled until the dialog closes, so it blocks input to the app.
+    pub modal: bool,
+}
+
+/// Platform window operations. Handles are opaque integers (HWND on Windows,
+/// X11 window ids for an EWMH backend) so tools stay backend-agnostic.
+pub trait WindowBackend: Send + Sync {
+    /// Visible top-level windows, ordered from the top of the z-order.
+    fn list_windows(&self) -> Result<Vec<WindowInfo>>;
+    fn focus(&self, handle: u64) -> Result<()>;
+    fn set_state(&self, handle: u64, state: WindowState) -> Result<()>;
+    /// Moves and/or resizes; `None` keeps the current value.
+    fn set_bounds(&self, handle: u64, x: Option<i32>, y: Option<i32>, width: Option<i32>, height: Option<i32>) -> Result<()>;
+    fn close(&self, handle: u64) -> Result<()>;
+    /// Owned dialog windows, ordered from the top of the z-order.
+    fn list_dialogs(&self) -> Result<Vec<DialogWindow>>;
+}
+
+pub fn find_window(backend: &dyn WindowBackend, handle: u64) -> Result<WindowInfo> {
+    backend
+        .list_windows()?
+        .into_iter()
+        .find(|w| w.handle == handle)
+        .ok_or_else(|| anyhow!("No window with handle {}", handle))
+}
+
+/// Windows whose title contains `title` and whose process is named `process`, both
+/// case-insensitive; `None` matches anything.
+pub fn filter_windows(backend: &dyn WindowBackend, title: Option<&str>, process: Option<&str>) -> Result<Vec<WindowInfo>> {
+    let title = title.map(str::to_lowercase);
+    let process = process.map(str::to_lowercase);
+    Ok(backend
+        .list_windows()?
+        .into_iter()
+        .filter(|w| title.as_ref().is_none_or(|t| w.title.to_lowercase().contains(t)))
+        .filter(|w| process.as_ref().is_none_or(|p| w.process_name.to_lowercase() == *p))
+        .collect())
+}
+
+/// An operation requested through the `window_action` tool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowAction {
+    Focus,
+    SetState(WindowState),
+    Close,
+    Move { x: i32, y: i32 },
+    Resize { width: i32, height: i32 },
+}
+
+impl WindowAction {
+    /// Reads `action` and the coordinates it needs from tool arguments.
+    pub fn from_args(args: &Value) -> Result<Self> {
+        let action = args["action"].as_str().ok_or_else(|| anyhow!("Missing action"))?;
+        let coord = |name: &str| args[name].as_i64().and_then(|v| i32::try_from(v).ok());
+        let pair = |a: &str, b: &str| match (coord(a), coord(b)) {
+            (Some(first), Some(second)) => Ok((first, second)),
+            _ => Err(anyhow!("{} needs integer {} and {}", action, a, b)),
+        };
+
+        match action {
+            "focus" => Ok(Self::Focus),
+            "minimize" => Ok(Self::SetState(WindowState::Minimized)),
+            "maximize" => Ok(Self::SetState(WindowState::Maximized)),
+            "restore" => Ok(Self::SetState(WindowState::Normal)),
+            "close" => Ok(Self::Close),
+            "move" => pair("x", "y").map(|(x, y)| Self::Move { x, y }),
+            "resize" => match pair("width", "height")? {
+                (width, height) if width > 0 && height > 0 => Ok(Self::Resize { width, height }),
+                _ => Err(anyhow!("resize needs a positive width and height")),
+            },
+            _ => Err(anyhow!("Unknown action: {}", action)),
+        }
+    }
+
+    pub fn apply(self, backend: &dyn WindowBackend, handle: u64) -> Result<()> {
+        match self {
+            Self::Focus => backend.focus(handle),
+            Self::SetState(state) => backend.set_state(handle, state),
+            Self::Close => backend.close(handle),
+            Self::Move { x, y } => backend.set_bounds(handle, Some(x), Some(y), None, None),
+            Self::Resize { width, height } => backend.set_bounds(handle, None, None, Some(width), Some(height)),
+        }
+    }
+}
+
+#[cfg(windows)]
+pub use win32::Win32Backend;
+
+#[cfg(windows)]
+mod win32 {
+    use super::{DialogWindow, WindowBackend, WindowInfo, WindowState};
+    use anyhow::{Result, anyhow};
+    use windows::Win32::Foundation::*;
+    use windows::Win32::Graphics::Dwm::*;
+    use windows::Win32::Graphics::Gdi::*;
+    use windows::Win32::System::Threading::*;
+    use windows::Win32::UI::Input::KeyboardAndMouse::IsWindowEnabled;
+    use windows::Win32::UI::WindowsAndMessaging::*;
+    use windows::core::PWSTR;
+
+    pub struct Win32Backend;
+
+    impl Win32Backend {
+        fn hwnd(handle: u64) -> Result<HWND> {
+            let hwnd = HWND(handle as isize as *mut _);
+            if unsafe { IsWindow(hwnd) }.as_bool() {
+                Ok(hwnd)
+            } else {
+                Err(anyhow!("Window {} no longer exists", handle))
+            }
+        }
+    }
+
+    impl WindowBackend for Win32Backend {
+        fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+            let monitors = monitors();
+            let mut handles: Vec<HWND> = Vec::new();
+            unsafe {
+                EnumWindows(Some(collect_window), LPARAM(&mut handles as *mut _ as isize))?;
+            }
+
+            let windows = handles
+                .into_iter()
+                .filter(|&hwnd| is_app_window(hwnd))
+                .enumerate()
+                .map(|(z_order, hwnd)| describe(hwnd, z_order, &monitors))
+                .collect();
+            Ok(windows)
+        }
+
+        fn focus(&self, handle: u64) -> Result<()> {
+            let hwnd = Self::hwnd(handle)?;
+            unsafe {
+                if IsIconic(hwnd).as_bool() {
+                    let _ = ShowWindow(hwnd, SW_RESTORE);
+                }
+                if !SetForegroundWindow(hwnd).as_bool() {
+                    return Err(anyhow!("Windows refused to bring window {} to the foreground", handle));
+                }
+            }
+            Ok(())
+        }
+
+        fn set_state(&self, handle: u64, state: WindowState) -> Result<()> {
+            let hwnd = Self::hwnd(handle)?;
+            let command = match state {
+                WindowState::Normal => SW_RESTORE,
+                WindowState::Minimized => SW_MINIMIZE,
+                WindowState::Maximized => SW_MAXIMIZE,
+            };
+            unsafe {
+                let _ = ShowWindow(hwnd, command);
+            }
+            Ok(())
+        }
+
+        fn set_bounds(&self, handle: u64, x: Option<i32>, y: Option<i32>, width: Option<i32>, height: Option<i32>) -> Result<()> {
+            let hwnd = Self::hwnd(handle)?;
+            unsafe {
+                // A maximized window ignores new bounds until it is restored.
+                if IsZoomed(hwnd).as_bool() || IsIconic(hwnd).as_bool() {
+                    let _ = ShowWindow(hwnd, SW_RESTORE);
+                }
+
+                let mut rect = RECT::default();
+                GetWindowRect(hwnd, &mut rect)?;
+                SetWindowPos(
+                    hwnd,
+                    None,
+                    x.unwrap_or(rect.left),
+                    y.unwrap_or(rect.top),
+                    width.unwrap_or(rect.right - rect.left),
+                    height.unwrap_or(rect.bottom - rect.top),
+                    SWP_NOZORDER | SWP_NOACTIVATE,
+                )?;
+            }
+            Ok(())
+        }
+
+        fn close(&self, handle: u64) -> Result<()> {
+            let hwnd = Self::hwnd(handle)?;
+            // WM_CLOSE lets the application prompt to save instead of being killed.
+            unsafe { PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0))? };
+            Ok(())
+        }
+
+        fn list_dialogs(&self) -> Result<Vec<DialogWindow>> {
+            let monitors = monitors();
+            let mut handles: Vec<HWND> = Vec::new();
+            unsafe {
+                EnumWindows(Some(collect_window), LPARAM(&mut handles as *mut _ as isize))?;
+            }
+
+            let dialogs = handles
+                .into_iter()
+                .filter(|&hwnd| unsafe { IsWindowVisible(hwnd) }.as_bool())
+                .filter_map(|hwnd| {
+                    let owner = unsafe { GetWindow(hwnd, GW_OWNER) }.ok()?;
+                    let modal = !unsafe { IsWindowEnabled(owner) }.as_bool();
+                    (modal || is_dialog_class(hwnd)).then_some((hwnd, owner, modal))
+                })
+                .enumerate()
+                .map(|(z_order, (hwnd, owner, modal))| DialogWindow {
+                    window: describe(hwnd, z_order, &monitors),
+                    owner: owner.0 as usize as u64,
+                    owner_title: window_title(owner),
+                    modal,
+                })
+                .collect();
+            Ok(dialogs)
+        }
+    }
+
+    /// `#32770` is the class of message boxes, task dialogs and resource-based dialogs.
+    fn is_dialog_class(hwnd: HWND) -> bool {
+        let mut class = [0u16; 64];
+        let len = unsafe { GetClassNameW(hwnd, &mut class) }.max(0) as usize;
+        String::from_utf16_lossy(&class[..len]) == "#32770"
+    }
+
+    fn window_title(hwnd: HWND) -> String {
+        let mut title = [0u16; 512];
+        let len = unsafe { GetWindowTextW(hwnd, &mut title) }.max(0) as usize;
+        String::from_utf16_lossy(&title[..len])
+    }
+
+    unsafe extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let handles = unsafe { &mut *(lparam.0 as *mut Vec<HWND>) };
+        handles.push(hwnd);
+        TRUE
+    }
+
+    unsafe extern "system" fn collect_monitor(monitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, lparam: LPARAM) -> BOOL {
+        let monitors = unsafe { &mut *(lparam.0 as *mut Vec<HMONITOR>) };
+        monitors.push(monitor);
+        TRUE
+    }
+
+    fn monitors() -> Vec<HMONITOR> {
+        let mut monitors: Vec<HMONITOR> = Vec::new();
+        unsafe {
+            let _ = EnumDisplayMonitors(None, None, Some(collect_monitor), LPARAM(&mut monitors as *mut _ as isize));
+        }
+        monitors
+    }
+
+    /// Visible, titled, uncloaked windows that are not tool windows: what Alt+Tab shows.
+    fn is_app_window(hwnd: HWND) -> bool {
+        unsafe {
+            if !IsWindowVisible(hwnd).as_bool() || GetWindowTextLengthW(hwnd) == 0 {
+                return false;
+            }
+            let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+            if ex_style & WS_EX_TOOLWINDOW.0 != 0 {
+                return false;
+            }
+            let mut cloaked: u32 = 0;
+            let cloaked_ok = DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_CLOAKED,
+                &mut cloaked as *mut _ as _,
+                std::mem::size_of::<u32>() as u32,
+            ).is_ok();
+            !(cloaked_ok && cloaked != 0)
+        }
+    }
+
+    fn describe(hwnd: HWND, z_order: usize, monitors: &[HMONITOR]) -> WindowInfo {
+        unsafe {
+            let mut process_id = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+
+            // Extended frame bounds exclude the invisible resize borders of Windows 10+.
+            let mut rect = RECT::default();
+            if DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_EXTENDED_FRAME_BOUNDS,
+                &mut rect as *mut _ as _,
+                std::mem::size_of::<RECT>() as u32,
+            ).is_err() {
+                let _ = GetWindowRect(hwnd, &mut rect);
+            }
+
+            let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+            let state = if IsIconic(hwnd).as_bool() {
+                WindowState::Minimized
+            } else if IsZoomed(hwnd).as_bool() {
+                WindowState::Maximized
+            } else {
+                WindowState::Normal
+            };
+
+            WindowInfo {
+                handle: hwnd.0 as usize as u64,
+                title: window_title(hwnd),
+                process_name: process_name(process_id).unwrap_or_default(),
+                process_id,
+                bounds: [rect.left, rect.top, rect.right, rect.bottom],
+                monitor: monitors.iter().position(|&m| m == monitor).unwrap_or(0),
+                z_order,
+                state,
+            }
+        }
+    }
+
+    fn process_name(process_id: u32) -> Option<String> {
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+            let mut buffer = [0u16; 260];
+            let mut size = buffer.len() as u32;
+            let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size);
+            let _ = CloseHandle(process);
+            result.ok()?;
+
+            let path = String::from_utf16_lossy(&buffer[..size as usize]);
+            path.rsplit('\\').next().map(String::from)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// In-memory backend: a fixed window list and a log of the operations requested.
+    #[derive(Default)]
+    struct FakeBackend {
+        windows: Vec<WindowInfo>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl WindowBackend for FakeBackend {
+        fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+            Ok(self.windows.clone())
+        }
+
+        fn focus(&self, handle: u64) -> Result<()> {
+            self.calls.lock().unwrap().push(format!("focus {}", handle));
+            Ok(())
+        }
+
+        fn set_state(&self, handle: u64, state: WindowState) -> Result<()> {
+            self.calls.lock().unwrap().push(format!("state {} {:?}", handle, state));
+            Ok(())
+        }
+
+        fn set_bounds(&self, handle: u64, x: Option<i32>, y: Option<i32>, width: Option<i32>, height: Option<i32>) -> Result<()> {
+            self.calls.lock().unwrap().push(format!("bounds {} {:?} {:?} {:?} {:?}", handle, x, y, width, height));
+            Ok(())
+        }
+
+        fn close(&self, handle: u64) -> Result<()> {
+            self.calls.lock().unwrap().push(format!("close {}", handle));
+            Ok(())
+        }
+
+        fn list_dialogs(&self) -> Result<Vec<DialogWindow>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn window(handle: u64, title: &str, process_name: &str) -> WindowInfo {
+        WindowInfo {
+            handle,
+            title: title.to_string(),
+            process_name: process_name.to_string(),
+            process_id: handle as u32,
+            bounds: [0, 0, 800, 600],
+            monitor: 0,
+            z_order: handle as usize,
+            state: WindowState::Normal,
+        }
+    }
+
+    fn backend() -> FakeBackend {
+        FakeBackend {
+            windows: vec![
+                window(1, "Untitled - Notepad", "notepad.exe"),
+                window(2, "README.md - Notepad", "notepad.exe"),
+                window(3, "Inbox - Outlook", "OUTLOOK.EXE"),
+            ],
+            ..FakeBackend::default()
+        }
+    }
+
+    fn handles(windows: &[WindowInfo]) -> Vec<u64> {
+        windows.iter().map(|w| w.handle).collect()
+    }
+
+    #[test]
+    fn find_window_matches_handle() {
+        let backend = backend();
+        assert_eq!(find_window(&backend, 3).unwrap().title, "Inbox - Outlook");
+        assert!(find_window(&backend, 42).is_err());
+    }
+
+    #[test]
+    fn filters_by_title_substring_ignoring_case() {
+        let windows = filter_windows(&backend(), Some("notepad"), None).unwrap();
+        assert_eq!(handles(&windows), [1, 2]);
+    }
+
+    #[test]
+    fn filters_by_exact_process_name_ignoring_case() {
+        assert_eq!(handles(&filter_windows(&backend(), None, Some("outlook.exe")).unwrap()), [3]);
+        assert!(filter_windows(&backend(), None, Some("outlook")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn combines_filters_and_keeps_order() {
+        let backend = backend();
+        assert_eq!(handles(&filter_windows(&backend, Some("readme"), Some("NOTEPAD.EXE")).unwrap()), [2]);
+        assert_eq!(handles(&filter_windows(&backend, None, None).unwrap()), [1, 2, 3]);
+    }
+
+    fn action(args: Value) -> Result<WindowAction> {
+        WindowAction::from_args(&args)
+    }
+
+    #[test]
+    fn parses_actions_without_coordinates() {
+        assert_eq!(action(json!({ "action": "focus" })).unwrap(), WindowAction::Focus);
+        assert_eq!(action(json!({ "action": "minimize" })).unwrap(), WindowAction::SetState(WindowState::Minimized));
+        assert_eq!(action(json!({ "action": "maximize" })).unwrap(), WindowAction::SetState(WindowState::Maximized));
+        assert_eq!(action(json!({ "action": "restore" })).unwrap(), WindowAction::SetState(WindowState::Normal));
+        assert_eq!(action(json!({ "action": "close", "x": 5 })).unwrap(), WindowAction::Close);
+    }
+
+    #[test]
+    fn move_needs_both_coordinates_in_range() {
+        assert_eq!(action(json!({ "action": "move", "x": -1920, "y": 0 })).unwrap(), WindowAction::Move { x: -1920, y: 0 });
+        for args in [
+            json!({ "action": "move", "x": 10 }),
+            json!({ "action": "move", "x": 10, "y": "20" }),
+            json!({ "action": "move", "x": 10.5, "y": 20 }),
+            json!({ "action": "move", "x": i64::from(i32::MAX) + 1, "y": 0 }),
+        ] {
+            assert!(action(args.clone()).is_err(), "accepted {}", args);
+        }
+    }
+
+    #[test]
+    fn resize_needs_a_positive_size() {
+        assert_eq!(action(json!({ "action": "resize", "width": 640, "height": 480 })).unwrap(), WindowAction::Resize { width: 640, height: 480 });
+        for args in [
+            json!({ "action": "resize", "width": 640 }),
+            json!({ "action": "resize", "width": 0, "height": 480 }),
+            json!({ "action": "resize", "width": 640, "height": -1 }),
+        ] {
+            assert!(action(args.clone()).is_err(), "accepted {}", args);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_or_missing_actions() {
+        assert_eq!(action(json!({ "action": "shake" })).unwrap_err().to_string(), "Unknown action: shake");
+        assert!(action(json!({})).is_err());
+    }
+
+    #[test]
+    fn actions_map_to_backend_operations() {
+        let backend = backend();
+        WindowAction::Move { x: 10, y: 20 }.apply(&backend, 1).unwrap();
+        WindowAction::Resize { width: 640, height: 480 }.apply(&backend, 1).unwrap();
+        WindowAction::SetState(WindowState::Maximized).apply(&backend, 2).unwrap();
+        WindowAction::Close.apply(&backend, 3).unwrap();
+        assert_eq!(*backend.calls.lock().unwrap(), [
+            "bounds 1 Some(10) Some(20) None None",
+            "bounds 1 None None Some(640) Some(480)",
+            "state 2 Maximized",
+            "close 3",
+        ]);
+    }
+}